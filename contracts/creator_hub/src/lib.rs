@@ -1,16 +1,65 @@
 #![no_std]
-use soroban_sdk::{ contract, contracterror, contractimpl, contracttype, symbol_short, map, Address, Env, Map };
+use soroban_sdk::{ contract, contracterror, contractimpl, contracttype, symbol_short, token, vec, Address, Env, IntoVal, Symbol };
 
 #[derive(Clone)]
 #[contracttype]
-pub enum DataKey { Admin, Bal(Address) }
+pub enum DataKey {
+    Admin,
+    Bal(Address, Address),  // (owner, fee token) -> claimable amount
+    FeeSource(Address),
+    Reserved(Address),      // fee token -> total credited but not yet claimed
+    Split,                  // default FeeSplit
+    CreatorSplit(Address),  // per-creator FeeSplit override
+    StakerRewards,          // kale_pass_treasury distributing the staker share
+}
+
+/// Fee shares in basis points; always sums to 10_000.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct FeeSplit { pub platform_bps: u32, pub creator_bps: u32, pub staker_bps: u32 }
+
+/// Default split used until the admin sets one: roughly the original 3/2/1.
+const DEFAULT_SPLIT: FeeSplit = FeeSplit { platform_bps: 5_000, creator_bps: 3_333, staker_bps: 1_667 };
 
 #[contracterror]
 #[derive(Copy, Clone, PartialEq, Eq)]
-pub enum Err { NotInitialized=1, AlreadyInitialized=2, NotAdmin=3 }
+pub enum Err { NotInitialized=1, AlreadyInitialized=2, NotAdmin=3, NotFeeSource=4, Unfunded=5, BadSplit=6 }
 
 fn read_admin(env: &Env) -> Address { env.storage().instance().get::<_, Address>(&DataKey::Admin).unwrap_or_else(|| soroban_sdk::Env::panic_with_error(env, Err::NotInitialized)) }
 fn require_admin(env: &Env) { read_admin(env).require_auth(); }
+fn is_fee_source(env: &Env, who: &Address) -> bool { env.storage().instance().get::<_, bool>(&DataKey::FeeSource(who.clone())).unwrap_or(false) }
+fn read_default_split(env: &Env) -> FeeSplit { env.storage().instance().get::<_, FeeSplit>(&DataKey::Split).unwrap_or(DEFAULT_SPLIT) }
+fn read_split(env: &Env, creator: &Address) -> FeeSplit {
+    env.storage().instance().get::<_, FeeSplit>(&DataKey::CreatorSplit(creator.clone()))
+        .unwrap_or_else(|| read_default_split(env))
+}
+fn check_split(env: &Env, s: &FeeSplit) {
+    if s.platform_bps as u64 + s.creator_bps as u64 + s.staker_bps as u64 != 10_000 { Env::panic_with_error(env, Err::BadSplit); }
+}
+
+/// Split `total` into (platform, creator, staker) shares. Each share is first rounded down;
+/// the leftover dust (at most 2 units) then goes one unit at a time to the share with the
+/// largest discarded remainder, ties going to platform, then creator, then staker.
+fn split_amount(total: i128, s: &FeeSplit) -> [i128; 3] {
+    let bps = [s.platform_bps as i128, s.creator_bps as i128, s.staker_bps as i128];
+    let (q, r) = (total / 10_000, total % 10_000);
+    let mut out = [0i128; 3];
+    let mut rem = [0i128; 3];
+    for i in 0..3 {
+        out[i] = q * bps[i] + r * bps[i] / 10_000;
+        rem[i] = r * bps[i] % 10_000;
+    }
+    let mut dust = total - out[0] - out[1] - out[2];
+    while dust > 0 {
+        let mut best = 0;
+        for i in 1..3 { if rem[i] > rem[best] { best = i; } }
+        out[best] += 1;
+        rem[best] = -1;
+        dust -= 1;
+    }
+    out
+}
+fn read_reserved(env: &Env, token: &Address) -> i128 { env.storage().instance().get::<_, i128>(&DataKey::Reserved(token.clone())).unwrap_or(0) }
 
 #[contract]
 pub struct CreatorHub;
@@ -22,32 +71,110 @@ impl CreatorHub {
         env.storage().instance().set(&DataKey::Admin, &admin);
     }
 
-    /// Credit fee balances. For now callable by admin. Split proportions example: platform 3, creator 2, stakers 1 of 6 parts total.
-    pub fn credit_fees(env: Env, platform: Address, creator: Address, staker_pool: Address, total_fee: i128) {
+    /// Allow or revoke a contract (e.g. round_engine) crediting fees without the admin.
+    pub fn set_fee_source(env: Env, source: Address, allowed: bool) {
         require_admin(&env);
+        env.storage().instance().set(&DataKey::FeeSource(source.clone()), &allowed);
+        env.events().publish((symbol_short!("fee_src"),), (source, allowed));
+    }
+
+    /// Set the default platform/creator/staker split (admin only).
+    pub fn set_split(env: Env, split: FeeSplit) {
+        require_admin(&env);
+        check_split(&env, &split);
+        env.storage().instance().set(&DataKey::Split, &split);
+        env.events().publish((symbol_short!("split"),), split);
+    }
+
+    /// Override the split for one creator's rounds, or drop the override with `None` (admin only).
+    pub fn set_creator_split(env: Env, creator: Address, split: Option<FeeSplit>) {
+        require_admin(&env);
+        let key = DataKey::CreatorSplit(creator.clone());
+        match split.clone() {
+            Some(s) => { check_split(&env, &s); env.storage().instance().set(&key, &s); }
+            None => env.storage().instance().remove(&key),
+        }
+        env.events().publish((symbol_short!("split"), creator), split);
+    }
+
+    /// Split applied to fees credited for `creator` (their override, else the default).
+    pub fn get_split(env: Env, creator: Address) -> FeeSplit { read_split(&env, &creator) }
+
+    /// Split applied to creators without an override.
+    pub fn get_default_split(env: Env) -> FeeSplit { read_default_split(&env) }
+
+    /// Send the staker share to `treasury` (kale_pass_treasury) for pro-rata distribution to
+    /// KALE Pass stakers, or back to the `staker_pool` balance with `None` (admin only).
+    /// The hub must be the treasury's reward source; while it isn't, the staker share keeps
+    /// going to `staker_pool` so fee credits (and the claims that trigger them) never fail.
+    pub fn set_staker_rewards(env: Env, treasury: Option<Address>) {
+        require_admin(&env);
+        match treasury.clone() {
+            Some(t) => env.storage().instance().set(&DataKey::StakerRewards, &t),
+            None => env.storage().instance().remove(&DataKey::StakerRewards),
+        }
+        env.events().publish((symbol_short!("stk_rwd"),), treasury);
+    }
+
+    /// Credit fee balances in `token`. Callable by the admin, which must have transferred
+    /// `total_fee` of `token` to the hub beforehand, or by a registered fee source (`caller`),
+    /// from which the hub pulls `total_fee` itself: the fee only leaves the source if the
+    /// credit goes through.
+    /// The fee is divided according to `get_split(creator)`; the staker share goes to the
+    /// staker rewards treasury when one is set and accepts it, else to `staker_pool`'s balance.
+    pub fn credit_fees(env: Env, caller: Address, token: Address, platform: Address, creator: Address, staker_pool: Address, total_fee: i128) {
+        caller.require_auth();
+        let by_admin = caller == read_admin(&env);
+        if !by_admin && !is_fee_source(&env, &caller) { Env::panic_with_error(&env, Err::NotFeeSource); }
         // avoid negative totals
-        let tf = if total_fee < 0 { 0 } else { total_fee } as i128;
-        let sixth = tf / 6;
-        let p = sixth * 3;
-        let c = sixth * 2;
-        let s = tf - p - c;
+        let tf = if total_fee < 0 { 0 } else { total_fee };
+        if !by_admin && tf > 0 {
+            token::Client::new(&env, &token).transfer(&caller, &env.current_contract_address(), &tf);
+        }
+        // every credited unit must be backed by tokens held by the hub
+        let mut reserved = read_reserved(&env, &token).checked_add(tf).unwrap_or_else(|| Env::panic_with_error(&env, Err::Unfunded));
+        if token::Client::new(&env, &token).balance(&env.current_contract_address()) < reserved { Env::panic_with_error(&env, Err::Unfunded); }
+        let [p, c, mut s] = split_amount(tf, &read_split(&env, &creator));
+        if let Some(treasury) = env.storage().instance().get::<_, Address>(&DataKey::StakerRewards) {
+            if s > 0 {
+                let args = vec![&env, env.current_contract_address().into_val(&env), token.clone().into_val(&env), s.into_val(&env)];
+                // notify first, transfer once it's accepted: a rejection leaves `s` reserved here
+                match env.try_invoke_contract::<(), soroban_sdk::Error>(&treasury, &Symbol::new(&env, "notify_reward"), args) {
+                    Ok(Ok(())) => {
+                        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &treasury, &s);
+                        reserved -= s;
+                        s = 0;
+                    }
+                    _ => env.events().publish((symbol_short!("rwd_fail"),), (treasury, token.clone(), s)),
+                }
+            }
+        }
+        env.storage().instance().set(&DataKey::Reserved(token.clone()), &reserved);
         for (addr, amt) in [(platform, p), (creator, c), (staker_pool, s)].iter() {
-            let key = DataKey::Bal(addr.clone());
+            if *amt == 0 { continue; }
+            let key = DataKey::Bal(addr.clone(), token.clone());
             let cur = env.storage().instance().get::<_, i128>(&key).unwrap_or(0);
             let newv = cur.saturating_add(*amt);
             env.storage().instance().set(&key, &newv);
         }
     }
 
-    pub fn claim(env: Env, who: Address) -> i128 {
-        let key = DataKey::Bal(who.clone());
+    /// Pay out `who`'s whole `token` balance to `to` (defaults to `who`).
+    pub fn claim(env: Env, who: Address, token: Address, to: Option<Address>) -> i128 {
+        who.require_auth();
+        let key = DataKey::Bal(who.clone(), token.clone());
         let cur = env.storage().instance().get::<_, i128>(&key).unwrap_or(0);
-        if cur != 0 { env.storage().instance().set(&key, &0i128); }
+        if cur == 0 { return 0; }
+        env.storage().instance().set(&key, &0i128);
+        env.storage().instance().set(&DataKey::Reserved(token.clone()), &(read_reserved(&env, &token) - cur));
+        let to = to.unwrap_or(who.clone());
+        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &to, &cur);
+        env.events().publish((symbol_short!("claim"), who), (token, to, cur));
         cur
     }
 
-    pub fn balance_of(env: Env, who: Address) -> i128 {
-        env.storage().instance().get::<_, i128>(&DataKey::Bal(who)).unwrap_or(0)
+    pub fn balance_of(env: Env, who: Address, token: Address) -> i128 {
+        env.storage().instance().get::<_, i128>(&DataKey::Bal(who, token)).unwrap_or(0)
     }
 }
 
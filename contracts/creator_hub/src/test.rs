@@ -2,7 +2,28 @@
 extern crate std;
 
 use super::*;
-use soroban_sdk::{testutils::Address as TestAddress, Address, Env};
+use soroban_sdk::{contract, contractimpl, symbol_short, testutils::Address as TestAddress, token, Address, Env};
+
+// ---- Mock kale_pass_treasury: records the last reward notification ----
+#[contract]
+struct MockRewards;
+#[contractimpl]
+impl MockRewards {
+    pub fn notify_reward(env: Env, caller: Address, token: Address, amount: i128) {
+        caller.require_auth();
+        if env.storage().instance().has(&symbol_short!("reject")) {
+            // the hub isn't this treasury's reward source
+            Env::panic_with_error(&env, soroban_sdk::Error::from_contract_error(10));
+        }
+        env.storage().instance().set(&symbol_short!("last"), &(token, amount));
+    }
+    pub fn reject(env: Env) {
+        env.storage().instance().set(&symbol_short!("reject"), &true);
+    }
+    pub fn last(env: Env) -> Option<(Address, i128)> {
+        env.storage().instance().get(&symbol_short!("last"))
+    }
+}
 
 // Helpers
 fn setup_env_auth() -> (
@@ -12,19 +33,21 @@ fn setup_env_auth() -> (
     Address,
     Address,
     Address,
+    Address,
 ) {
     let env = Env::default();
     env.mock_all_auths(); // allow admin-gated calls to pass
-    let id = env.register_contract(None, CreatorHub);
+    let id = env.register(CreatorHub, ());
     let c = CreatorHubClient::new(&env, &id);
 
     let admin = <Address as TestAddress>::generate(&env);
     let platform = <Address as TestAddress>::generate(&env);
     let creator = <Address as TestAddress>::generate(&env);
     let stakers = <Address as TestAddress>::generate(&env);
+    let fee_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
 
     c.init(&admin);
-    (env, c, admin, platform, creator, stakers)
+    (env, c, admin, platform, creator, stakers, fee_token)
 }
 
 fn setup_env_plain() -> (
@@ -34,100 +57,317 @@ fn setup_env_plain() -> (
     Address,
     Address,
     Address,
+    Address,
 ) {
     let env = Env::default(); // NO mock_all_auths -> auth will be enforced
-    let id = env.register_contract(None, CreatorHub);
+    let id = env.register(CreatorHub, ());
     let c = CreatorHubClient::new(&env, &id);
 
     let admin = <Address as TestAddress>::generate(&env);
     let platform = <Address as TestAddress>::generate(&env);
     let creator = <Address as TestAddress>::generate(&env);
     let stakers = <Address as TestAddress>::generate(&env);
+    let fee_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
 
     c.init(&admin);
-    (env, c, admin, platform, creator, stakers)
+    (env, c, admin, platform, creator, stakers, fee_token)
+}
+
+/// Send `amount` of `fee_token` to the hub, as a fee source does before crediting.
+fn fund(env: &Env, c: &CreatorHubClient, fee_token: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, fee_token).mint(&c.address, &amount);
+}
+
+fn token_balance(env: &Env, fee_token: &Address, who: &Address) -> i128 {
+    token::Client::new(env, fee_token).balance(who)
 }
 
 // --- Tests ---
 
 #[test]
 fn credit_splits_and_claim() {
-    let (_env, c, _admin, platform, creator, stakers) = setup_env_auth();
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
 
     // 600 splits into 300/200/100  (3/2/1 of 6)
-    c.credit_fees(&platform, &creator, &stakers, &600i128);
-    assert_eq!(c.balance_of(&platform), 300);
-    assert_eq!(c.balance_of(&creator), 200);
-    assert_eq!(c.balance_of(&stakers), 100);
+    fund(&env, &c, &t, 600);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &600i128);
+    assert_eq!(c.balance_of(&platform, &t), 300);
+    assert_eq!(c.balance_of(&creator, &t), 200);
+    assert_eq!(c.balance_of(&stakers, &t), 100);
 
-    // Claim resets only the claimer
-    let got = c.claim(&creator);
+    // Claim pays and resets only the claimer
+    let got = c.claim(&creator, &t, &None);
     assert_eq!(got, 200);
-    assert_eq!(c.balance_of(&creator), 0);
-    assert_eq!(c.balance_of(&platform), 300);
-    assert_eq!(c.balance_of(&stakers), 100);
+    assert_eq!(token_balance(&env, &t, &creator), 200);
+    assert_eq!(c.balance_of(&creator, &t), 0);
+    assert_eq!(c.balance_of(&platform, &t), 300);
+    assert_eq!(c.balance_of(&stakers, &t), 100);
 }
 
 #[test]
 fn credit_rounding_and_negatives() {
-    let (_env, c, _admin, platform, creator, stakers) = setup_env_auth();
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
 
-    // total_fee = 7 -> sixth=1 => platform=3, creator=2, stakers gets the rest: 2
-    c.credit_fees(&platform, &creator, &stakers, &7i128);
-    assert_eq!(c.balance_of(&platform), 3);
-    assert_eq!(c.balance_of(&creator), 2);
-    assert_eq!(c.balance_of(&stakers), 2);
+    // total_fee = 7 at 50/33.33/16.67% -> 3.5 / 2.33 / 1.17 rounded down to 3/2/1;
+    // the 1 unit of dust goes to the largest remainder (platform)
+    fund(&env, &c, &t, 7);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &7i128);
+    assert_eq!(c.balance_of(&platform, &t), 4);
+    assert_eq!(c.balance_of(&creator, &t), 2);
+    assert_eq!(c.balance_of(&stakers, &t), 1);
 
     // negative -> treated as 0
-    c.credit_fees(&platform, &creator, &stakers, &(-10i128));
-    assert_eq!(c.balance_of(&platform), 3);
-    assert_eq!(c.balance_of(&creator), 2);
-    assert_eq!(c.balance_of(&stakers), 2);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &(-10i128));
+    assert_eq!(c.balance_of(&platform, &t), 4);
+    assert_eq!(c.balance_of(&creator, &t), 2);
+    assert_eq!(c.balance_of(&stakers, &t), 1);
 }
 
 #[test]
 fn multiple_credits_accumulate_then_claim_all() {
-    let (_env, c, _admin, platform, creator, stakers) = setup_env_auth();
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
 
-    c.credit_fees(&platform, &creator, &stakers, &60i128); // 30 / 20 / 10
-    c.credit_fees(&platform, &creator, &stakers, &12i128); //  6 /  4 /  2
-    assert_eq!(c.balance_of(&platform), 36);
-    assert_eq!(c.balance_of(&creator), 24);
-    assert_eq!(c.balance_of(&stakers), 12);
+    fund(&env, &c, &t, 72);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &60i128); // 30 / 20 / 10
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &12i128); //  6 /  4 /  2
+    assert_eq!(c.balance_of(&platform, &t), 36);
+    assert_eq!(c.balance_of(&creator, &t), 24);
+    assert_eq!(c.balance_of(&stakers, &t), 12);
 
     // claim stakers only
-    assert_eq!(c.claim(&stakers), 12);
-    assert_eq!(c.balance_of(&stakers), 0);
+    assert_eq!(c.claim(&stakers, &t, &None), 12);
+    assert_eq!(c.balance_of(&stakers, &t), 0);
+    assert_eq!(token_balance(&env, &t, &c.address), 60);
 }
 
 #[test]
 fn balance_of_new_user_is_zero() {
-    let (env, c, _admin, _p, _cr, _s) = setup_env_auth();
+    let (env, c, _admin, _p, _cr, _s, t) = setup_env_auth();
     let outsider = <Address as TestAddress>::generate(&env);
-    assert_eq!(c.balance_of(&outsider), 0);
+    assert_eq!(c.balance_of(&outsider, &t), 0);
+    assert_eq!(c.claim(&outsider, &t, &None), 0);
 }
 
 #[test]
 #[should_panic] // must not allow double init
 fn init_only_once_panics() {
-    let (_env, c, admin, _p, _cr, _s) = setup_env_auth();
+    let (_env, c, admin, _p, _cr, _s, _t) = setup_env_auth();
     c.init(&admin); // -> panic AlreadyInitialized
 }
 
 #[test]
 #[should_panic] // admin auth should be enforced when auth isn't mocked
 fn credit_requires_admin_auth() {
-    let (_env, c, _admin, platform, creator, stakers) = setup_env_plain();
-    c.credit_fees(&platform, &creator, &stakers, &1i128); // -> panic
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_plain();
+    fund(&env, &c, &t, 1);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &1i128); // -> panic
 }
 
 #[test]
 fn large_values_do_not_panic() {
-    let (_env, c, _admin, platform, creator, stakers) = setup_env_auth();
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
 
     // Very large fee to exercise saturating_add path; ensure no panic
     let huge = i128::MAX / 2;
-    c.credit_fees(&platform, &creator, &stakers, &huge);
-    let sum = c.balance_of(&platform) + c.balance_of(&creator) + c.balance_of(&stakers);
+    fund(&env, &c, &t, huge);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &huge);
+    let sum = c.balance_of(&platform, &t) + c.balance_of(&creator, &t) + c.balance_of(&stakers, &t);
     assert!(sum > 0);
 }
+
+#[test]
+fn registered_fee_source_can_credit() {
+    let (env, c, _admin, platform, creator, stakers, t) = setup_env_auth();
+    let engine = <Address as TestAddress>::generate(&env);
+    c.set_fee_source(&engine, &true);
+    // fee sources don't prefund: the hub pulls the fee from them
+    token::StellarAssetClient::new(&env, &t).mint(&engine, &6);
+    c.credit_fees(&engine, &t, &platform, &creator, &stakers, &6i128);
+    assert_eq!(c.balance_of(&platform, &t), 3);
+    assert_eq!(c.balance_of(&creator, &t), 2);
+    assert_eq!(c.balance_of(&stakers, &t), 1);
+    assert_eq!(token_balance(&env, &t, &engine), 0);
+    assert_eq!(token_balance(&env, &t, &c.address), 6);
+}
+
+#[test]
+#[should_panic] // unregistered callers cannot credit, even with their own auth
+fn unregistered_caller_cannot_credit() {
+    let (env, c, _admin, platform, creator, stakers, t) = setup_env_auth();
+    let intruder = <Address as TestAddress>::generate(&env);
+    fund(&env, &c, &t, 6);
+    c.credit_fees(&intruder, &t, &platform, &creator, &stakers, &6i128); // -> NotFeeSource
+}
+
+#[test]
+#[should_panic] // revoked sources lose access
+fn revoked_fee_source_cannot_credit() {
+    let (env, c, _admin, platform, creator, stakers, t) = setup_env_auth();
+    let engine = <Address as TestAddress>::generate(&env);
+    c.set_fee_source(&engine, &true);
+    c.set_fee_source(&engine, &false);
+    fund(&env, &c, &t, 6);
+    c.credit_fees(&engine, &t, &platform, &creator, &stakers, &6i128);
+}
+
+#[test]
+#[should_panic] // credits must be backed by tokens already sent to the hub
+fn unfunded_credit_panics() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    fund(&env, &c, &t, 10);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &6i128);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &6i128); // only 4 unreserved -> Unfunded
+}
+
+#[test]
+fn claim_to_other_recipient() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    let cold_wallet = <Address as TestAddress>::generate(&env);
+    fund(&env, &c, &t, 600);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &600i128);
+    assert_eq!(c.claim(&platform, &t, &Some(cold_wallet.clone())), 300);
+    assert_eq!(token_balance(&env, &t, &cold_wallet), 300);
+    assert_eq!(token_balance(&env, &t, &platform), 0);
+    assert_eq!(c.balance_of(&platform, &t), 0);
+}
+
+#[test]
+fn balances_are_tracked_per_token() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    let other = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    fund(&env, &c, &t, 60);
+    fund(&env, &c, &other, 6);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &60i128);
+    c.credit_fees(&admin, &other, &platform, &creator, &stakers, &6i128);
+    assert_eq!(c.balance_of(&platform, &t), 30);
+    assert_eq!(c.balance_of(&platform, &other), 3);
+
+    assert_eq!(c.claim(&platform, &other, &None), 3);
+    assert_eq!(c.balance_of(&platform, &t), 30);
+    assert_eq!(token_balance(&env, &other, &platform), 3);
+    assert_eq!(token_balance(&env, &t, &platform), 0);
+}
+
+#[test]
+#[should_panic] // claimant auth is enforced when we don't mock
+fn claim_requires_claimant_auth() {
+    let (_env, c, _admin, platform, _creator, _stakers, t) = setup_env_plain();
+    c.claim(&platform, &t, &None);
+}
+
+#[test]
+fn default_split_is_readable() {
+    let (_env, c, _admin, _platform, creator, _stakers, _t) = setup_env_auth();
+    assert_eq!(c.get_split(&creator), FeeSplit { platform_bps: 5_000, creator_bps: 3_333, staker_bps: 1_667 });
+    assert_eq!(c.get_default_split(), c.get_split(&creator));
+}
+
+#[test]
+fn admin_split_and_creator_override() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    let other_creator = <Address as TestAddress>::generate(&env);
+    c.set_split(&FeeSplit { platform_bps: 7_000, creator_bps: 2_000, staker_bps: 1_000 });
+    c.set_creator_split(&creator, &Some(FeeSplit { platform_bps: 2_000, creator_bps: 6_000, staker_bps: 2_000 }));
+    assert_eq!(c.get_split(&other_creator).platform_bps, 7_000);
+    assert_eq!(c.get_split(&creator).creator_bps, 6_000);
+    // overrides don't change the default
+    assert_eq!(c.get_default_split(), FeeSplit { platform_bps: 7_000, creator_bps: 2_000, staker_bps: 1_000 });
+
+    fund(&env, &c, &t, 2_000);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &1_000i128);
+    assert_eq!(c.balance_of(&platform, &t), 200);
+    assert_eq!(c.balance_of(&creator, &t), 600);
+    assert_eq!(c.balance_of(&stakers, &t), 200);
+
+    c.credit_fees(&admin, &t, &platform, &other_creator, &stakers, &1_000i128);
+    assert_eq!(c.balance_of(&platform, &t), 900);
+    assert_eq!(c.balance_of(&other_creator, &t), 200);
+    assert_eq!(c.balance_of(&stakers, &t), 300);
+
+    // dropping the override falls back to the default
+    c.set_creator_split(&creator, &None);
+    assert_eq!(c.get_split(&creator).platform_bps, 7_000);
+}
+
+#[test]
+fn rounding_dust_goes_to_largest_remainder_then_platform() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    fund(&env, &c, &t, 10);
+
+    // 2 units at 33.33/33.33/33.34% -> 0.6666 / 0.6666 / 0.6668: staker has the largest
+    // remainder, the second unit breaks the platform/creator tie towards platform
+    c.set_split(&FeeSplit { platform_bps: 3_333, creator_bps: 3_333, staker_bps: 3_334 });
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &2i128);
+    assert_eq!(c.balance_of(&platform, &t), 1);
+    assert_eq!(c.balance_of(&creator, &t), 0);
+    assert_eq!(c.balance_of(&stakers, &t), 1);
+
+    // exact tie between creator and staker -> creator
+    c.set_split(&FeeSplit { platform_bps: 0, creator_bps: 5_000, staker_bps: 5_000 });
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &1i128);
+    assert_eq!(c.balance_of(&creator, &t), 1);
+    assert_eq!(c.balance_of(&stakers, &t), 1);
+}
+
+#[test]
+#[should_panic] // split must sum to 10_000
+fn set_split_bad_sum_panics() {
+    let (_env, c, _admin, _p, _cr, _s, _t) = setup_env_auth();
+    c.set_split(&FeeSplit { platform_bps: 5_000, creator_bps: 3_000, staker_bps: 1_000 });
+}
+
+#[test]
+#[should_panic] // overrides are validated too
+fn set_creator_split_bad_sum_panics() {
+    let (_env, c, _admin, _p, creator, _s, _t) = setup_env_auth();
+    c.set_creator_split(&creator, &Some(FeeSplit { platform_bps: 10_000, creator_bps: 1, staker_bps: 0 }));
+}
+
+#[test]
+#[should_panic] // admin auth enforced when we don't mock
+fn set_split_requires_admin_auth() {
+    let (_env, c, _admin, _p, _cr, _s, _t) = setup_env_plain();
+    c.set_split(&FeeSplit { platform_bps: 10_000, creator_bps: 0, staker_bps: 0 });
+}
+
+#[test]
+fn staker_share_goes_to_rewards_treasury() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    let treasury = env.register(MockRewards, ());
+    c.set_staker_rewards(&Some(treasury.clone()));
+
+    fund(&env, &c, &t, 600);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &600i128);
+    assert_eq!(c.balance_of(&stakers, &t), 0);
+    // no zero balance entry is left behind for staker_pool
+    env.as_contract(&c.address, || {
+        assert!(!env.storage().instance().has(&DataKey::Bal(stakers.clone(), t.clone())));
+    });
+    assert_eq!(token_balance(&env, &t, &treasury), 100);
+    assert_eq!(MockRewardsClient::new(&env, &treasury).last(), Some((t.clone(), 100)));
+
+    // the 100 sent away is no longer reserved: the next credit only needs fresh funds
+    fund(&env, &c, &t, 6);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &6i128);
+    assert_eq!(c.balance_of(&platform, &t), 303);
+
+    // switching back credits the staker_pool balance again
+    c.set_staker_rewards(&None);
+    fund(&env, &c, &t, 6);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &6i128);
+    assert_eq!(c.balance_of(&stakers, &t), 1);
+}
+
+#[test]
+fn rejected_staker_rewards_fall_back_to_staker_pool() {
+    let (env, c, admin, platform, creator, stakers, t) = setup_env_auth();
+    let treasury = env.register(MockRewards, ());
+    MockRewardsClient::new(&env, &treasury).reject();
+    c.set_staker_rewards(&Some(treasury.clone()));
+
+    fund(&env, &c, &t, 600);
+    c.credit_fees(&admin, &t, &platform, &creator, &stakers, &600i128);
+    assert_eq!(c.balance_of(&stakers, &t), 100);
+    assert_eq!(token_balance(&env, &t, &treasury), 0);
+    assert_eq!(c.claim(&stakers, &t, &None), 100);
+}
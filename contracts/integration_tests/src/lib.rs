@@ -0,0 +1,6 @@
+#![no_std]
+//! End-to-end tests wiring the real oracle_adapter, round_engine, kale_pass_treasury and
+//! creator_hub together against a Stellar Asset Contract token and a mock Reflector feed.
+
+#[cfg(test)]
+mod test;
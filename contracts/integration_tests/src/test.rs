@@ -0,0 +1,274 @@
+extern crate std;
+
+use creator_hub::{CreatorHub, CreatorHubClient};
+use kale_pass_treasury::{KalePassTreasury, KalePassTreasuryClient, Tier};
+use oracle_adapter::reflector::{Asset as ReflectorAsset, Contract as ReflectorContract, PriceData};
+use oracle_adapter::{OracleAdapter, OracleAdapterClient};
+use round_engine::{RoundEngine, RoundEngineClient, RoundStatus, SettleMode};
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{Address as _, Ledger},
+    token::{StellarAssetClient, TokenClient},
+    vec, Address, Env, String, Symbol, Vec,
+};
+
+// ---- Mock Reflector implementing the full interface the adapter binds to ----
+// Keeps only the latest price per asset, 14 decimals like the live feeds.
+#[contract]
+struct MockReflector;
+
+#[contractimpl]
+impl MockReflector {
+    pub fn set_price(e: Env, asset: ReflectorAsset, price: i128) {
+        let record = PriceData { price, timestamp: e.ledger().timestamp() };
+        e.storage().instance().set(&asset, &record);
+    }
+}
+
+#[contractimpl]
+impl ReflectorContract for MockReflector {
+    fn base(e: Env) -> ReflectorAsset {
+        ReflectorAsset::Other(Symbol::new(&e, "USD"))
+    }
+    fn assets(e: Env) -> Vec<ReflectorAsset> {
+        Vec::new(&e)
+    }
+    fn decimals(_e: Env) -> u32 {
+        14
+    }
+    fn price(e: Env, asset: ReflectorAsset, timestamp: u64) -> Option<PriceData> {
+        Self::lastprice(e, asset).filter(|p| p.timestamp <= timestamp)
+    }
+    fn lastprice(e: Env, asset: ReflectorAsset) -> Option<PriceData> {
+        e.storage().instance().get(&asset)
+    }
+    fn prices(e: Env, asset: ReflectorAsset, _records: u32) -> Option<Vec<PriceData>> {
+        Self::lastprice(e.clone(), asset).map(|p| Vec::from_array(&e, [p]))
+    }
+    fn x_last_price(e: Env, base_asset: ReflectorAsset, quote_asset: ReflectorAsset) -> Option<PriceData> {
+        let b = Self::lastprice(e.clone(), base_asset)?;
+        let q = Self::lastprice(e, quote_asset)?;
+        Some(PriceData { price: b.price * 100_000_000_000_000 / q.price, timestamp: b.timestamp.min(q.timestamp) })
+    }
+    fn x_price(e: Env, base_asset: ReflectorAsset, quote_asset: ReflectorAsset, timestamp: u64) -> Option<PriceData> {
+        Self::x_last_price(e, base_asset, quote_asset).filter(|p| p.timestamp <= timestamp)
+    }
+    fn x_prices(e: Env, base_asset: ReflectorAsset, quote_asset: ReflectorAsset, _records: u32) -> Option<Vec<PriceData>> {
+        Self::x_last_price(e.clone(), base_asset, quote_asset).map(|p| Vec::from_array(&e, [p]))
+    }
+    fn twap(e: Env, asset: ReflectorAsset, _records: u32) -> Option<i128> {
+        Self::lastprice(e, asset).map(|p| p.price)
+    }
+    fn x_twap(e: Env, base_asset: ReflectorAsset, quote_asset: ReflectorAsset, _records: u32) -> Option<i128> {
+        Self::x_last_price(e, base_asset, quote_asset).map(|p| p.price)
+    }
+    fn resolution(_e: Env) -> u32 {
+        300
+    }
+    fn period(_e: Env) -> Option<u64> {
+        Some(86_400)
+    }
+    fn last_timestamp(e: Env) -> u64 {
+        e.ledger().timestamp()
+    }
+    fn version(_e: Env) -> u32 {
+        1
+    }
+    fn admin(_e: Env) -> Option<Address> {
+        None
+    }
+}
+
+/// All four contracts deployed and wired together the way they are on testnet.
+struct Stack {
+    env: Env,
+    admin: Address,
+    feeder: Address,
+    platform: Address,
+    staker_pool: Address,
+    usdc: Address,
+    kale: Address,
+    reflector: MockReflectorClient<'static>,
+    oracle: OracleAdapterClient<'static>,
+    engine: RoundEngineClient<'static>,
+    treasury: KalePassTreasuryClient<'static>,
+    hub: CreatorHubClient<'static>,
+}
+
+const FEE_BPS: u32 = 1_000; // 10% of winnings
+const KALE: i128 = 10_000_000; // 1 KALE at the SAC's 7 decimals
+
+fn setup() -> Stack {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.timestamp = 1_700_000_000);
+    let admin = Address::generate(&env);
+    let feeder = Address::generate(&env);
+    let platform = Address::generate(&env);
+    let staker_pool = Address::generate(&env);
+    let usdc = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let kale = env.register_stellar_asset_contract_v2(admin.clone()).address();
+
+    let reflector = MockReflectorClient::new(&env, &env.register(MockReflector, ()));
+    let oracle = OracleAdapterClient::new(&env, &env.register(OracleAdapter, ()));
+    let engine = RoundEngineClient::new(&env, &env.register(RoundEngine, ()));
+    let treasury = KalePassTreasuryClient::new(&env, &env.register(KalePassTreasury, ()));
+    let hub = CreatorHubClient::new(&env, &env.register(CreatorHub, ()));
+
+    oracle.init(&admin, &reflector.address);
+    oracle.set_feeder(&admin, &feeder);
+    let btc = String::from_str(&env, "BTC");
+    oracle.upsert_asset(&admin, &btc, &8, &600);
+    oracle.set_reflector_source(&admin, &btc, &ReflectorAsset::Other(Symbol::new(&env, "BTC")), &None);
+
+    // 100 KALE -> 20%, 500 KALE -> 40% off the fee
+    treasury.init(&admin);
+    treasury.set_token(&kale);
+    treasury.set_tiers(&vec![
+        &env,
+        Tier { threshold: 0, discount_bps: 0 },
+        Tier { threshold: 100 * KALE as u128, discount_bps: 2_000 },
+        Tier { threshold: 500 * KALE as u128, discount_bps: 4_000 },
+    ]);
+    treasury.set_reward_source(&hub.address);
+
+    hub.init(&admin);
+    hub.set_fee_source(&engine.address, &true);
+    hub.set_staker_rewards(&Some(treasury.address.clone()));
+
+    engine.init(&admin);
+    engine.set_oracle(&oracle.address);
+    engine.set_token(&usdc);
+    engine.set_fee_config(&hub.address, &FEE_BPS, &platform, &staker_pool);
+    engine.set_treasury(&treasury.address);
+
+    Stack { env, admin, feeder, platform, staker_pool, usdc, kale, reflector, oracle, engine, treasury, hub }
+}
+
+impl Stack {
+    fn user(&self, usdc: i128) -> Address {
+        let who = Address::generate(&self.env);
+        if usdc > 0 {
+            StellarAssetClient::new(&self.env, &self.usdc).mint(&who, &usdc);
+        }
+        who
+    }
+
+    fn usdc_balance(&self, who: &Address) -> i128 {
+        TokenClient::new(&self.env, &self.usdc).balance(who)
+    }
+
+    fn advance(&self, secs: u64) {
+        self.env.ledger().with_mut(|l| {
+            l.timestamp += secs;
+            l.sequence_number += 1;
+        });
+    }
+
+    /// Quote BTC on the Reflector feed (14 decimals) and have the feeder pull it.
+    fn quote_btc(&self, usd: i128) {
+        let btc = ReflectorAsset::Other(Symbol::new(&self.env, "BTC"));
+        self.reflector.set_price(&btc, &(usd * 100_000_000_000_000));
+        self.oracle.pull_from_reflector(&self.feeder, &String::from_str(&self.env, "BTC"));
+    }
+}
+
+#[test]
+fn reflector_round_pays_winners_with_discounted_fees() {
+    let s = setup();
+    let creator = s.user(0);
+    let alice = s.user(1_000);
+    let bob = s.user(1_000);
+    let carol = s.user(2_000);
+
+    // alice holds a 40% KALE Pass tier
+    StellarAssetClient::new(&s.env, &s.kale).mint(&alice, &(500 * KALE));
+    s.treasury.stake(&alice, &(500 * KALE));
+    assert_eq!(s.treasury.get_discount_bps(&alice), 4_000);
+
+    let btc = String::from_str(&s.env, "BTC");
+    let id = s.engine.create_round(&creator, &btc, &60, &300, &SettleMode::Spot);
+    s.engine.join(&id, &alice, &1, &1_000);
+    s.engine.join(&id, &bob, &1, &1_000);
+    s.engine.join(&id, &carol, &0, &2_000);
+    assert_eq!(s.usdc_balance(&s.engine.address), 4_000);
+
+    s.advance(60);
+    s.quote_btc(60_000);
+    s.engine.lock(&id);
+    s.advance(300);
+    s.quote_btc(61_000);
+    s.engine.settle(&id);
+
+    let r = s.engine.get_round(&id);
+    assert!(r.status == RoundStatus::Settled);
+    assert_eq!(r.lock_price, Some(6_000_000_000_000_000_000));
+    assert_eq!(r.settle_price, Some(6_100_000_000_000_000_000));
+    assert_eq!(r.winner, Some(1));
+
+    // each Up stake wins 1_000 of the Down pool; the 10% fee is 100, or 60 for alice
+    assert_eq!(s.engine.claim(&id, &alice), 1_940);
+    assert_eq!(s.engine.claim(&id, &bob), 1_900);
+    assert!(s.engine.try_claim(&id, &carol).is_err());
+    assert_eq!(s.usdc_balance(&alice), 1_940);
+    assert_eq!(s.usdc_balance(&s.engine.address), 0);
+
+    // 160 of fees split 50/33.33/16.67; the staker share went to the treasury for stakers
+    assert_eq!(s.hub.balance_of(&s.platform, &s.usdc), 80);
+    assert_eq!(s.hub.balance_of(&creator, &s.usdc), 53);
+    assert_eq!(s.hub.balance_of(&s.staker_pool, &s.usdc), 0);
+    assert_eq!(s.usdc_balance(&s.treasury.address), 27);
+    assert_eq!(s.hub.claim(&creator, &s.usdc, &None), 53);
+    assert_eq!(s.usdc_balance(&creator), 53);
+
+    // alice is the only staker, so she receives the whole staker share
+    assert_eq!(s.treasury.pending_rewards(&alice).get(s.usdc.clone()), Some(27));
+    s.treasury.claim_rewards(&alice);
+    assert_eq!(s.usdc_balance(&alice), 1_967);
+}
+
+#[test]
+fn manual_feed_twap_round_and_circuit_breaker() {
+    let s = setup();
+    let creator = s.user(0);
+    let up = s.user(500);
+    let down = s.user(1_500);
+    let code = String::from_str(&s.env, "BTC");
+    s.oracle.set_guardrails(&s.admin, &code, &2_000, &60); // halt on a >20% move
+
+    let id = s.engine.create_round(&creator, &code, &120, &300, &SettleMode::TwapWindow(120));
+    s.engine.join(&id, &up, &1, &500);
+    s.engine.join(&id, &down, &0, &1_500);
+
+    // feeder pushes 100.00 then 90.00 during the two minutes before lock
+    let t0 = s.env.ledger().timestamp();
+    s.oracle.push_price(&s.feeder, &code, &10_000_000_000, &8, &t0);
+    s.advance(60);
+    s.oracle.push_price(&s.feeder, &code, &9_000_000_000, &8, &(t0 + 60));
+    s.advance(60);
+    s.engine.lock(&id);
+    // 100 for 60s, 90 for 60s, at 14 decimals
+    assert_eq!(s.engine.get_round(&id).lock_price, Some(9_500_000_000_000_000));
+
+    // 85.00 for the last two minutes of the round, then a bad push at settle time trips
+    // the breaker, so settlement waits for the admin
+    s.advance(180);
+    s.oracle.push_price(&s.feeder, &code, &8_500_000_000, &8, &s.env.ledger().timestamp());
+    s.advance(120);
+    s.oracle.push_price(&s.feeder, &code, &1_000_000, &8, &s.env.ledger().timestamp());
+    assert!(s.oracle.get_halt(&code).is_some());
+    assert!(s.engine.try_settle(&id).is_err());
+    s.oracle.acknowledge_halt(&s.admin, &code, &false);
+    s.engine.settle(&id);
+
+    // 85.00 over the whole window: Down wins
+    let r = s.engine.get_round(&id);
+    assert_eq!(r.settle_price, Some(8_500_000_000_000_000));
+    assert_eq!(r.winner, Some(0));
+    assert!(s.engine.try_claim(&id, &up).is_err());
+    // no KALE Pass: the full 10% fee on 500 of winnings
+    assert_eq!(s.engine.claim(&id, &down), 1_950);
+    assert_eq!(s.hub.balance_of(&s.platform, &s.usdc), 25);
+    // nobody staked KALE, so the staker share waits in the treasury for the first staker
+    assert_eq!(s.usdc_balance(&s.treasury.address), 8);
+}
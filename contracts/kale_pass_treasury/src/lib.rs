@@ -1,14 +1,34 @@
 #![no_std]
-use soroban_sdk::{contract, contracterror, contractimpl, contracttype, vec, Address, Env, Vec};
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, symbol_short, token, vec, Address, Env, Map, Vec};
 
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     Admin,
     Tiers,
-    Stake(Address),
+    Token,                         // KALE token escrowed by stakers
+    Stake(Address),                // escrowed balance per user
+    TotalStaked,
+    MigrationClosed,               // once set, admin_set_stake is disabled for good
+    SelfStaked(Address),           // user has deposited via `stake`; admin_set_stake can't touch them
+    UnbondSecs,                    // delay between unstake and withdraw
+    LockTerms,                     // Vec<LockTerm> offered to stakers
+    Unbonding(Address),            // Vec<Unbond> queued withdrawals per user
+    Locks(Address),                // Vec<Lock> fixed-term locks per user
+    RewardSource,                  // creator_hub allowed to notify rewards
+    RewardTokens,                  // Vec<Address> of every token ever notified
+    RewardAcc(Address),            // reward token -> accumulated reward per staked unit (x ACC_SCALE)
+    Undistributed(Address),        // reward token -> rewards not yet in the accumulator (x ACC_SCALE)
+    RewardDebt(Address, Address),  // (user, token) -> stake * acc at the user's last settlement
+    Pending(Address, Address),     // (user, token) -> settled but unclaimed rewards
 }
 
+/// Default unbonding delay: 7 days.
+const DEFAULT_UNBOND_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Fixed-point scale of the reward-per-share accumulator.
+const ACC_SCALE: i128 = 1_000_000_000_000;
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Tier {
@@ -16,6 +36,31 @@ pub struct Tier {
     pub discount_bps: u32, // 0..=10_000 (100%)
 }
 
+/// A lock duration on offer and the weight it gives locked stake (10_000 = 1x).
+#[derive(Clone)]
+#[contracttype]
+pub struct LockTerm {
+    pub secs: u64,
+    pub boost_bps: u32,
+}
+
+/// Part of a user's stake committed until `unlock_ts`.
+#[derive(Clone)]
+#[contracttype]
+pub struct Lock {
+    pub amount: u128,
+    pub unlock_ts: u64,
+    pub boost_bps: u32,
+}
+
+/// Unstaked KALE waiting out the unbonding period.
+#[derive(Clone)]
+#[contracttype]
+pub struct Unbond {
+    pub amount: u128,
+    pub release_ts: u64,
+}
+
 #[contracterror]
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Err {
@@ -23,6 +68,13 @@ pub enum Err {
     AlreadyInitialized = 2,
     NotAdmin = 3,
     BadInput = 4,
+    TokenNotSet = 5,
+    InsufficientStake = 6,
+    MigrationClosed = 7,
+    StakeLocked = 8,
+    NothingToWithdraw = 9,
+    NotRewardSource = 10,
+    SelfStaked = 11,
 }
 
 fn read_admin(env: &Env) -> Address {
@@ -35,28 +87,148 @@ fn require_admin(env: &Env) {
     read_admin(env).require_auth();
 }
 
+fn read_token(env: &Env) -> Address {
+    env.storage()
+        .instance()
+        .get::<_, Address>(&DataKey::Token)
+        .unwrap_or_else(|| soroban_sdk::Env::panic_with_error(env, Err::TokenNotSet))
+}
+
+fn read_stake(env: &Env, user: &Address) -> u128 {
+    env.storage()
+        .instance()
+        .get::<_, u128>(&DataKey::Stake(user.clone()))
+        .unwrap_or(0)
+}
+
+fn read_total(env: &Env) -> u128 {
+    env.storage()
+        .instance()
+        .get::<_, u128>(&DataKey::TotalStaked)
+        .unwrap_or(0)
+}
+
+/// Record `user`'s new escrowed balance and keep `TotalStaked` in step.
+/// Rewards earned on the old balance are settled first.
+fn write_stake(env: &Env, user: &Address, old: u128, new: u128) {
+    settle_rewards(env, user, old, new);
+    env.storage().instance().set(&DataKey::Stake(user.clone()), &new);
+    let total = read_total(env) - old + new;
+    env.storage().instance().set(&DataKey::TotalStaked, &total);
+}
+
+fn read_reward_tokens(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get::<_, Vec<Address>>(&DataKey::RewardTokens)
+        .unwrap_or(vec![env])
+}
+
+fn read_acc(env: &Env, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get::<_, i128>(&DataKey::RewardAcc(token.clone()))
+        .unwrap_or(0)
+}
+
+fn read_i128(env: &Env, key: &DataKey) -> i128 {
+    env.storage().instance().get::<_, i128>(key).unwrap_or(0)
+}
+
+/// Rewards of `token` earned by `stake` since the user's debt was last reset.
+fn accrued(env: &Env, user: &Address, token: &Address, stake: u128) -> i128 {
+    let earned = (stake as i128) * read_acc(env, token) / ACC_SCALE;
+    earned - read_i128(env, &DataKey::RewardDebt(user.clone(), token.clone()))
+}
+
+/// Move what `user` earned on `old` into `Pending` and restart accrual from `new`.
+fn settle_rewards(env: &Env, user: &Address, old: u128, new: u128) {
+    let tokens = read_reward_tokens(env);
+    for i in 0..tokens.len() {
+        let t = tokens.get_unchecked(i);
+        let earned = accrued(env, user, &t, old);
+        if earned != 0 {
+            let key = DataKey::Pending(user.clone(), t.clone());
+            let pending = read_i128(env, &key) + earned;
+            env.storage().instance().set(&key, &pending);
+        }
+        let debt = (new as i128) * read_acc(env, &t) / ACC_SCALE;
+        env.storage().instance().set(&DataKey::RewardDebt(user.clone(), t), &debt);
+    }
+}
+
+fn read_unbond_secs(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get::<_, u64>(&DataKey::UnbondSecs)
+        .unwrap_or(DEFAULT_UNBOND_SECS)
+}
+
+/// Locks still running at the current ledger time; expired ones are dropped.
+fn active_locks(env: &Env, user: &Address) -> Vec<Lock> {
+    let locks = env
+        .storage()
+        .instance()
+        .get::<_, Vec<Lock>>(&DataKey::Locks(user.clone()))
+        .unwrap_or(vec![env]);
+    let now = env.ledger().timestamp();
+    let mut out: Vec<Lock> = vec![env];
+    for i in 0..locks.len() {
+        let l = locks.get_unchecked(i);
+        if l.unlock_ts > now {
+            out.push_back(l);
+        }
+    }
+    out
+}
+
+fn locked_amount(locks: &Vec<Lock>) -> u128 {
+    let mut sum: u128 = 0;
+    for i in 0..locks.len() {
+        sum += locks.get_unchecked(i).amount;
+    }
+    sum
+}
+
+/// Stake weighted by running lock boosts; this is what tiers are matched against.
+fn effective_stake(env: &Env, user: &Address) -> u128 {
+    let locks = active_locks(env, user);
+    let mut eff = read_stake(env, user);
+    for i in 0..locks.len() {
+        let l = locks.get_unchecked(i);
+        let extra = l.boost_bps.saturating_sub(10_000) as u128;
+        eff = eff.saturating_add(l.amount.saturating_mul(extra) / 10_000);
+    }
+    eff
+}
+
+fn positive_amount(env: &Env, amount: i128) -> u128 {
+    if amount <= 0 {
+        soroban_sdk::Env::panic_with_error(env, Err::BadInput);
+    }
+    amount as u128
+}
+
 #[contract]
 pub struct KalePassTreasury;
 
 #[contractimpl]
 impl KalePassTreasury {
-    /// One-time init with default tiers: 0, 100, 500 KALE -> 0%, 20%, 40%
+    /// One-time init. Nobody gets a discount until the admin calls `set_tiers`: thresholds
+    /// are in base units of the KALE token (100 KALE = 1_000_000_000 at 7 decimals), which
+    /// isn't known yet.
     pub fn init(env: Env, admin: Address) {
         if env.storage().instance().has(&DataKey::Admin) {
             soroban_sdk::Env::panic_with_error(&env, Err::AlreadyInitialized);
         }
         env.storage().instance().set(&DataKey::Admin, &admin);
 
-        let tiers: Vec<Tier> = vec![
-            &env,
-            Tier { threshold: 0, discount_bps: 0 },
-            Tier { threshold: 100, discount_bps: 2000 },
-            Tier { threshold: 500, discount_bps: 4000 },
-        ];
+        let tiers: Vec<Tier> = vec![&env, Tier { threshold: 0, discount_bps: 0 }];
         env.storage().instance().set(&DataKey::Tiers, &tiers);
     }
 
-    /// Replace discount tiers (admin only). Validates monotonic thresholds and bps<=10000.
+    /// Replace discount tiers (admin only). Thresholds are in token base units.
+    /// Validates monotonic thresholds and bps<=10000.
     pub fn set_tiers(env: Env, tiers: Vec<Tier>) {
         require_admin(&env);
         // basic validation
@@ -74,15 +246,248 @@ impl KalePassTreasury {
         env.storage().instance().set(&DataKey::Tiers, &tiers);
     }
 
-    /// Prototype: admin sets a user’s staked amount (MVP; in prod this would be token-hook driven).
-    pub fn admin_set_stake(env: Env, user: Address, amount: u128) {
+    /// Set the KALE token that `stake` / `unstake` move (admin only).
+    pub fn set_token(env: Env, token: Address) {
+        require_admin(&env);
+        env.storage().instance().set(&DataKey::Token, &token);
+    }
+
+    /// Escrow `amount` KALE from `user` into the treasury.
+    pub fn stake(env: Env, user: Address, amount: i128) {
+        user.require_auth();
+        let add = positive_amount(&env, amount);
+        token::Client::new(&env, &read_token(&env)).transfer(&user, &env.current_contract_address(), &amount);
+        let old = read_stake(&env, &user);
+        write_stake(&env, &user, old, old + add);
+        env.storage().instance().set(&DataKey::SelfStaked(user.clone()), &true);
+        env.events().publish((symbol_short!("stake"), user), amount);
+    }
+
+    /// Start unbonding `amount` of `user`'s unlocked stake. It stops counting towards the
+    /// discount immediately and can be withdrawn once the unbonding period has passed.
+    pub fn unstake(env: Env, user: Address, amount: i128) {
+        user.require_auth();
+        let sub = positive_amount(&env, amount);
+        let old = read_stake(&env, &user);
+        if sub > old {
+            soroban_sdk::Env::panic_with_error(&env, Err::InsufficientStake);
+        }
+        if sub > old - locked_amount(&active_locks(&env, &user)) {
+            soroban_sdk::Env::panic_with_error(&env, Err::StakeLocked);
+        }
+        write_stake(&env, &user, old, old - sub);
+
+        let release_ts = env.ledger().timestamp().saturating_add(read_unbond_secs(&env));
+        let key = DataKey::Unbonding(user.clone());
+        let mut queue = env.storage().instance().get::<_, Vec<Unbond>>(&key).unwrap_or(vec![&env]);
+        queue.push_back(Unbond { amount: sub, release_ts });
+        env.storage().instance().set(&key, &queue);
+        env.events().publish((symbol_short!("unstake"), user), (amount, release_ts));
+    }
+
+    /// Pay out every unbonding entry of `user` whose release time has passed.
+    pub fn withdraw(env: Env, user: Address) -> i128 {
+        user.require_auth();
+        let key = DataKey::Unbonding(user.clone());
+        let queue = env.storage().instance().get::<_, Vec<Unbond>>(&key).unwrap_or(vec![&env]);
+        let now = env.ledger().timestamp();
+        let mut pending: Vec<Unbond> = vec![&env];
+        let mut ready: u128 = 0;
+        for i in 0..queue.len() {
+            let u = queue.get_unchecked(i);
+            if u.release_ts <= now {
+                ready += u.amount;
+            } else {
+                pending.push_back(u);
+            }
+        }
+        if ready == 0 {
+            soroban_sdk::Env::panic_with_error(&env, Err::NothingToWithdraw);
+        }
+        env.storage().instance().set(&key, &pending);
+        let amount = ready as i128;
+        token::Client::new(&env, &read_token(&env)).transfer(&env.current_contract_address(), &user, &amount);
+        env.events().publish((symbol_short!("withdraw"), user), amount);
+        amount
+    }
+
+    /// Commit `amount` of `user`'s unlocked stake for lock term `term` (index into the
+    /// configured terms). Until it expires the locked amount can't be unstaked and
+    /// counts `boost_bps / 10_000` times towards the discount tiers.
+    pub fn lock(env: Env, user: Address, amount: i128, term: u32) {
+        user.require_auth();
+        let add = positive_amount(&env, amount);
+        let terms = env
+            .storage()
+            .instance()
+            .get::<_, Vec<LockTerm>>(&DataKey::LockTerms)
+            .unwrap_or(vec![&env]);
+        let t = terms.get(term).unwrap_or_else(|| soroban_sdk::Env::panic_with_error(&env, Err::BadInput));
+        let mut locks = active_locks(&env, &user);
+        if add > read_stake(&env, &user) - locked_amount(&locks) {
+            soroban_sdk::Env::panic_with_error(&env, Err::InsufficientStake);
+        }
+        let unlock_ts = env.ledger().timestamp().saturating_add(t.secs);
+        locks.push_back(Lock { amount: add, unlock_ts, boost_bps: t.boost_bps });
+        env.storage().instance().set(&DataKey::Locks(user.clone()), &locks);
+        env.events().publish((symbol_short!("lock"), user), (amount, unlock_ts, t.boost_bps));
+    }
+
+    /// Set the unbonding delay applied to future `unstake` calls (admin only).
+    pub fn set_unbonding_secs(env: Env, secs: u64) {
         require_admin(&env);
+        env.storage().instance().set(&DataKey::UnbondSecs, &secs);
+    }
+
+    /// Replace the lock terms on offer (admin only). Boosts can't go below 1x.
+    pub fn set_lock_terms(env: Env, terms: Vec<LockTerm>) {
+        require_admin(&env);
+        for i in 0..terms.len() {
+            let t = terms.get_unchecked(i);
+            if t.secs == 0 || t.boost_bps < 10_000 {
+                soroban_sdk::Env::panic_with_error(&env, Err::BadInput);
+            }
+        }
+        env.storage().instance().set(&DataKey::LockTerms, &terms);
+    }
+
+    pub fn get_lock_terms(env: Env) -> Vec<LockTerm> {
         env.storage()
             .instance()
-            .set(&DataKey::Stake(user), &amount);
+            .get::<_, Vec<LockTerm>>(&DataKey::LockTerms)
+            .unwrap_or(vec![&env])
+    }
+
+    pub fn get_locks(env: Env, user: Address) -> Vec<Lock> {
+        active_locks(&env, &user)
+    }
+
+    pub fn get_unbonding(env: Env, user: Address) -> Vec<Unbond> {
+        env.storage()
+            .instance()
+            .get::<_, Vec<Unbond>>(&DataKey::Unbonding(user))
+            .unwrap_or(vec![&env])
+    }
+
+    pub fn effective_stake(env: Env, user: Address) -> u128 {
+        effective_stake(&env, &user)
     }
 
-    /// Read the effective discount (bps) for `user` given current tiers & their stake.
+    /// Migration only: set a user's escrowed stake carried over from the admin-managed MVP.
+    /// The difference is funded from (or returned to) the admin so every stake stays
+    /// token-backed. Only legacy balances can be set: users who have deposited with `stake`
+    /// are rejected, so the admin can never take tokens a user escrowed themselves.
+    /// Disabled permanently once `close_migration` is called.
+    pub fn admin_set_stake(env: Env, user: Address, amount: u128) {
+        require_admin(&env);
+        if env.storage().instance().has(&DataKey::MigrationClosed) {
+            soroban_sdk::Env::panic_with_error(&env, Err::MigrationClosed);
+        }
+        if env.storage().instance().has(&DataKey::SelfStaked(user.clone())) {
+            soroban_sdk::Env::panic_with_error(&env, Err::SelfStaked);
+        }
+        let admin = read_admin(&env);
+        let token = token::Client::new(&env, &read_token(&env));
+        let old = read_stake(&env, &user);
+        if amount < locked_amount(&active_locks(&env, &user)) {
+            soroban_sdk::Env::panic_with_error(&env, Err::StakeLocked);
+        }
+        if amount > old {
+            token.transfer(&admin, &env.current_contract_address(), &((amount - old) as i128));
+        } else if amount < old {
+            token.transfer(&env.current_contract_address(), &admin, &((old - amount) as i128));
+        }
+        write_stake(&env, &user, old, amount);
+    }
+
+    /// One-shot: permanently disable `admin_set_stake` once legacy stakes are migrated.
+    pub fn close_migration(env: Env) {
+        require_admin(&env);
+        env.storage().instance().set(&DataKey::MigrationClosed, &true);
+    }
+
+    /// Set the creator_hub contract allowed to call `notify_reward` (admin only).
+    pub fn set_reward_source(env: Env, source: Address) {
+        require_admin(&env);
+        env.storage().instance().set(&DataKey::RewardSource, &source);
+    }
+
+    /// Distribute `amount` of `token` across current stakers pro rata to their escrowed stake.
+    /// The reward source transfers the tokens here once this call returns, in the same
+    /// transaction, so a rejected notification leaves them with the source. If nothing is
+    /// staked the amount is held back and added to the next distribution, as is the remainder
+    /// of the per-unit division, so no part of a reward is ever dropped.
+    pub fn notify_reward(env: Env, caller: Address, token: Address, amount: i128) {
+        caller.require_auth();
+        let source = env.storage().instance().get::<_, Address>(&DataKey::RewardSource);
+        if source != Some(caller) {
+            soroban_sdk::Env::panic_with_error(&env, Err::NotRewardSource);
+        }
+        if amount <= 0 {
+            return;
+        }
+        let mut tokens = read_reward_tokens(&env);
+        if !tokens.contains(&token) {
+            tokens.push_back(token.clone());
+            env.storage().instance().set(&DataKey::RewardTokens, &tokens);
+        }
+        let undistributed_key = DataKey::Undistributed(token.clone());
+        let total = read_total(&env) as i128;
+        let scaled = amount * ACC_SCALE + read_i128(&env, &undistributed_key);
+        if total == 0 {
+            env.storage().instance().set(&undistributed_key, &scaled);
+        } else {
+            let acc = read_acc(&env, &token) + scaled / total;
+            env.storage().instance().set(&DataKey::RewardAcc(token.clone()), &acc);
+            env.storage().instance().set(&undistributed_key, &(scaled % total));
+        }
+        env.events().publish((symbol_short!("reward"), token), amount);
+    }
+
+    /// Unclaimed staking rewards of `user`, per reward token.
+    pub fn pending_rewards(env: Env, user: Address) -> Map<Address, i128> {
+        let stake = read_stake(&env, &user);
+        let tokens = read_reward_tokens(&env);
+        let mut out: Map<Address, i128> = Map::new(&env);
+        for i in 0..tokens.len() {
+            let t = tokens.get_unchecked(i);
+            let owed = read_i128(&env, &DataKey::Pending(user.clone(), t.clone())) + accrued(&env, &user, &t, stake);
+            out.set(t, owed);
+        }
+        out
+    }
+
+    /// Pay out every pending reward of `user`; returns what was paid per token.
+    pub fn claim_rewards(env: Env, user: Address) -> Map<Address, i128> {
+        user.require_auth();
+        let stake = read_stake(&env, &user);
+        settle_rewards(&env, &user, stake, stake);
+        let tokens = read_reward_tokens(&env);
+        let mut paid: Map<Address, i128> = Map::new(&env);
+        for i in 0..tokens.len() {
+            let t = tokens.get_unchecked(i);
+            let key = DataKey::Pending(user.clone(), t.clone());
+            let owed = read_i128(&env, &key);
+            if owed > 0 {
+                env.storage().instance().set(&key, &0i128);
+                token::Client::new(&env, &t).transfer(&env.current_contract_address(), &user, &owed);
+                paid.set(t, owed);
+            }
+        }
+        env.events().publish((symbol_short!("rewards"), user), paid.clone());
+        paid
+    }
+
+    pub fn get_stake(env: Env, user: Address) -> u128 {
+        read_stake(&env, &user)
+    }
+
+    pub fn total_staked(env: Env) -> u128 {
+        read_total(&env)
+    }
+
+    /// Read the effective discount (bps) for `user` given current tiers & their escrowed stake
+    /// (weighted by any running lock boosts).
     pub fn get_discount_bps(env: Env, user: Address) -> u32 {
         let tiers = env
             .storage()
@@ -90,11 +495,7 @@ impl KalePassTreasury {
             .get::<_, Vec<Tier>>(&DataKey::Tiers)
             .unwrap_or(vec![&env]); // empty vec if not initialized (defensive)
 
-        let bal = env
-            .storage()
-            .instance()
-            .get::<_, u128>(&DataKey::Stake(user))
-            .unwrap_or(0);
+        let bal = effective_stake(&env, &user);
 
         let mut best: u32 = 0;
         // Use index-based access to avoid iterator Option confusion on Soroban Vec
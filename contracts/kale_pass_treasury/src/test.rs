@@ -3,8 +3,8 @@ extern crate std;
 
 use super::*;
 use soroban_sdk::{
-    testutils::Address as TestAddress,
-    vec,
+    testutils::{Address as TestAddress, Ledger},
+    token, vec,
     Address, Env, Vec,
 };
 
@@ -14,7 +14,7 @@ fn setup_auth() -> (Env, KalePassTreasuryClient<'static>, Address, Address, Addr
     let env = Env::default();
     env.mock_all_auths(); // allow admin-gated calls without signatures
 
-    let id = env.register_contract(None, KalePassTreasury);
+    let id = env.register(KalePassTreasury, ());
     let c = KalePassTreasuryClient::new(&env, &id);
 
     let admin   = <Address as TestAddress>::generate(&env);
@@ -23,12 +23,42 @@ fn setup_auth() -> (Env, KalePassTreasuryClient<'static>, Address, Address, Addr
     let user_c  = <Address as TestAddress>::generate(&env);
 
     c.init(&admin);
+    let kale = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    c.set_token(&kale);
+    // admin funds migrated stakes
+    token::StellarAssetClient::new(&env, &kale).mint(&admin, &1_000_000_000_000_000_000_000i128);
+    c.set_tiers(&test_tiers(&env));
     (env, c, admin, user_a, user_b, user_c)
 }
 
+/// Tiers most tests run with: 0, 100, 500 units -> 0%, 20%, 40%.
+fn test_tiers(env: &Env) -> Vec<Tier> {
+    vec![
+        env,
+        Tier { threshold: 0, discount_bps: 0 },
+        Tier { threshold: 100, discount_bps: 2000 },
+        Tier { threshold: 500, discount_bps: 4000 },
+    ]
+}
+
+/// KALE token configured on the treasury.
+fn kale(env: &Env, c: &KalePassTreasuryClient) -> Address {
+    env.as_contract(&c.address, || {
+        env.storage().instance().get::<_, Address>(&DataKey::Token).unwrap()
+    })
+}
+
+fn fund(env: &Env, c: &KalePassTreasuryClient, who: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, &kale(env, c)).mint(who, &amount);
+}
+
+fn kale_balance(env: &Env, c: &KalePassTreasuryClient, who: &Address) -> i128 {
+    token::Client::new(env, &kale(env, c)).balance(who)
+}
+
 fn setup_plain() -> (Env, KalePassTreasuryClient<'static>, Address, Address) {
     let env = Env::default(); // no mock_all_auths -> require_auth() is enforced
-    let id = env.register_contract(None, KalePassTreasury);
+    let id = env.register(KalePassTreasury, ());
     let c = KalePassTreasuryClient::new(&env, &id);
 
     let admin  = <Address as TestAddress>::generate(&env);
@@ -41,10 +71,28 @@ fn setup_plain() -> (Env, KalePassTreasuryClient<'static>, Address, Address) {
 // ----------------- tests -----------------
 
 #[test]
-fn default_tiers_and_get_discount() {
+fn no_discount_until_tiers_are_set() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let c = KalePassTreasuryClient::new(&env, &env.register(KalePassTreasury, ()));
+    let admin = <Address as TestAddress>::generate(&env);
+    let whale = <Address as TestAddress>::generate(&env);
+    c.init(&admin);
+    let kale = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    c.set_token(&kale);
+    token::StellarAssetClient::new(&env, &kale).mint(&admin, &1_000_000_000_000_000i128);
+    c.admin_set_stake(&whale, &1_000_000_000_000_000u128);
+    assert_eq!(c.get_discount_bps(&whale), 0);
+
+    c.set_tiers(&test_tiers(&env));
+    assert_eq!(c.get_discount_bps(&whale), 4000);
+}
+
+#[test]
+fn tiers_and_get_discount() {
     let (env, c, _admin, u1, u2, u3) = setup_auth();
 
-    // Default tiers: 0 -> 0%, 100 -> 20%, 500 -> 40%
+    // test tiers: 0 -> 0%, 100 -> 20%, 500 -> 40%
     c.admin_set_stake(&u1, &0u128);     // below first nonzero tier
     c.admin_set_stake(&u2, &100u128);   // exactly 100
     c.admin_set_stake(&u3, &500u128);   // exactly 500
@@ -117,7 +165,7 @@ fn set_tiers_bps_ceiling_panics() {
 fn admin_set_stake_and_read_back_via_discount() {
     let (_env, c, _admin, u1, _u2, _u3) = setup_auth();
     c.admin_set_stake(&u1, &42u128);
-    // with default tiers, 42 -> 0%
+    // with the test tiers, 42 -> 0%
     assert_eq!(c.get_discount_bps(&u1), 0);
     c.admin_set_stake(&u1, &500u128);
     assert_eq!(c.get_discount_bps(&u1), 4000);
@@ -164,3 +212,305 @@ fn large_values_do_not_panic() {
     c.admin_set_stake(&whale, &1_000_000_000_000_000_000u128);
     assert_eq!(c.get_discount_bps(&whale), 10_000);
 }
+
+#[test]
+fn stake_and_unstake_move_tokens_and_totals() {
+    let (env, c, _admin, u1, u2, _u3) = setup_auth();
+    fund(&env, &c, &u1, 1_000);
+    fund(&env, &c, &u2, 1_000);
+
+    c.stake(&u1, &600i128);
+    c.stake(&u2, &100i128);
+    assert_eq!(c.get_stake(&u1), 600);
+    assert_eq!(c.total_staked(), 700);
+    assert_eq!(kale_balance(&env, &c, &u1), 400);
+    assert_eq!(kale_balance(&env, &c, &c.address), 700);
+    assert_eq!(c.get_discount_bps(&u1), 4000);
+    assert_eq!(c.get_discount_bps(&u2), 2000);
+
+    // dropping below a tier threshold drops the discount right away
+    c.unstake(&u1, &150i128);
+    assert_eq!(c.get_stake(&u1), 450);
+    assert_eq!(c.total_staked(), 550);
+    assert_eq!(c.get_discount_bps(&u1), 2000);
+
+    // tokens come back only after the unbonding period
+    assert_eq!(kale_balance(&env, &c, &u1), 400);
+    env.ledger().with_mut(|l| l.timestamp += 7 * 24 * 60 * 60);
+    assert_eq!(c.withdraw(&u1), 150);
+    assert_eq!(kale_balance(&env, &c, &u1), 550);
+}
+
+#[test]
+#[should_panic] // cannot withdraw more than escrowed
+fn unstake_more_than_staked_panics() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &100i128);
+    c.unstake(&u1, &101i128); // -> InsufficientStake
+}
+
+#[test]
+#[should_panic] // amounts must be positive
+fn stake_zero_panics() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &0i128); // -> BadInput
+}
+
+#[test]
+#[should_panic] // staker auth enforced when we don't mock
+fn stake_requires_user_auth() {
+    let (_env, c, _admin, user) = setup_plain();
+    c.stake(&user, &10i128);
+}
+
+#[test]
+fn admin_set_stake_is_funded_by_admin() {
+    let (env, c, admin, u1, _u2, _u3) = setup_auth();
+    let before = kale_balance(&env, &c, &admin);
+    c.admin_set_stake(&u1, &500u128);
+    assert_eq!(kale_balance(&env, &c, &admin), before - 500);
+    assert_eq!(c.total_staked(), 500);
+
+    // lowering returns the difference to the admin
+    c.admin_set_stake(&u1, &200u128);
+    assert_eq!(kale_balance(&env, &c, &admin), before - 200);
+    assert_eq!(c.total_staked(), 200);
+
+    // migrated stake is withdrawable like any other
+    c.set_unbonding_secs(&0u64);
+    c.unstake(&u1, &200i128);
+    c.withdraw(&u1);
+    assert_eq!(kale_balance(&env, &c, &u1), 200);
+    assert_eq!(c.total_staked(), 0);
+}
+
+#[test]
+#[should_panic] // one-shot migration flag disables the admin setter
+fn admin_set_stake_after_close_migration_panics() {
+    let (_env, c, _admin, u1, _u2, _u3) = setup_auth();
+    c.admin_set_stake(&u1, &100u128);
+    c.close_migration();
+    c.admin_set_stake(&u1, &200u128); // -> MigrationClosed
+}
+
+#[test]
+fn admin_set_stake_cannot_touch_self_deposited_stake() {
+    let (env, c, admin, u1, _u2, _u3) = setup_auth();
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &1_000i128);
+    let before = kale_balance(&env, &c, &admin);
+    assert_eq!(
+        c.try_admin_set_stake(&u1, &0u128).err(),
+        Some(Ok(soroban_sdk::Error::from_contract_error(Err::SelfStaked as u32)))
+    );
+    assert_eq!(c.get_stake(&u1), 1_000);
+    assert_eq!(kale_balance(&env, &c, &admin), before);
+}
+
+#[test]
+#[should_panic] // unbonding entries are locked until their release time
+fn withdraw_before_release_panics() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    fund(&env, &c, &u1, 1_000);
+    c.set_unbonding_secs(&100u64);
+    c.stake(&u1, &500i128);
+    c.unstake(&u1, &500i128);
+    env.ledger().with_mut(|l| l.timestamp += 99);
+    c.withdraw(&u1); // -> NothingToWithdraw
+}
+
+#[test]
+fn withdraw_pays_only_matured_entries() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    fund(&env, &c, &u1, 1_000);
+    c.set_unbonding_secs(&100u64);
+    c.stake(&u1, &500i128);
+    c.unstake(&u1, &200i128);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    c.unstake(&u1, &100i128);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+
+    assert_eq!(c.withdraw(&u1), 200);
+    assert_eq!(c.get_unbonding(&u1).len(), 1);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    assert_eq!(c.withdraw(&u1), 100);
+    assert_eq!(kale_balance(&env, &c, &u1), 800);
+    assert_eq!(c.get_stake(&u1), 200);
+}
+
+fn with_lock_terms(env: &Env, c: &KalePassTreasuryClient) {
+    let terms: Vec<LockTerm> = vec![
+        env,
+        LockTerm { secs: 1_000, boost_bps: 15_000 }, // 1.5x
+        LockTerm { secs: 5_000, boost_bps: 30_000 }, // 3x
+    ];
+    c.set_lock_terms(&terms);
+}
+
+#[test]
+fn lock_boosts_discount_until_expiry() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    with_lock_terms(&env, &c);
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &200i128);
+    assert_eq!(c.get_discount_bps(&u1), 2000);
+
+    // 200 locked at 3x counts as 600
+    c.lock(&u1, &200i128, &1u32);
+    assert_eq!(c.effective_stake(&u1), 600);
+    assert_eq!(c.get_discount_bps(&u1), 4000);
+
+    env.ledger().with_mut(|l| l.timestamp += 5_000);
+    assert_eq!(c.effective_stake(&u1), 200);
+    assert_eq!(c.get_discount_bps(&u1), 2000);
+    assert_eq!(c.get_locks(&u1).len(), 0);
+}
+
+#[test]
+fn partial_lock_leaves_rest_unstakeable() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    with_lock_terms(&env, &c);
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &300i128);
+    c.lock(&u1, &100i128, &0u32);
+    assert_eq!(c.effective_stake(&u1), 350);
+    c.unstake(&u1, &200i128);
+    assert_eq!(c.get_stake(&u1), 100);
+}
+
+#[test]
+#[should_panic] // locked stake can't be unstaked before the lock expires
+fn unstake_locked_stake_panics() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    with_lock_terms(&env, &c);
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &300i128);
+    c.lock(&u1, &200i128, &0u32);
+    c.unstake(&u1, &101i128); // -> StakeLocked
+}
+
+#[test]
+#[should_panic] // can't lock more than the unlocked stake
+fn lock_more_than_stake_panics() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    with_lock_terms(&env, &c);
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &100i128);
+    c.lock(&u1, &60i128, &0u32);
+    c.lock(&u1, &60i128, &1u32); // -> InsufficientStake
+}
+
+#[test]
+#[should_panic] // unknown lock term index
+fn lock_unknown_term_panics() {
+    let (env, c, _admin, u1, _u2, _u3) = setup_auth();
+    with_lock_terms(&env, &c);
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &100i128);
+    c.lock(&u1, &100i128, &7u32); // -> BadInput
+}
+
+#[test]
+#[should_panic] // boosts below 1x are rejected
+fn set_lock_terms_rejects_sub_unit_boost() {
+    let (env, c, _admin, _u1, _u2, _u3) = setup_auth();
+    let terms: Vec<LockTerm> = vec![&env, LockTerm { secs: 10, boost_bps: 9_999 }];
+    c.set_lock_terms(&terms);
+}
+
+/// Reward token plus a registered reward source (stands in for creator_hub).
+fn rewards_setup(env: &Env, c: &KalePassTreasuryClient, admin: &Address) -> (Address, Address) {
+    let reward = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let source = <Address as TestAddress>::generate(env);
+    c.set_reward_source(&source);
+    (reward, source)
+}
+
+/// The source sends `amount` to the treasury and notifies it, as creator_hub does.
+fn distribute(env: &Env, c: &KalePassTreasuryClient, source: &Address, reward: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, reward).mint(&c.address, &amount);
+    c.notify_reward(source, reward, &amount);
+}
+
+#[test]
+fn rewards_split_pro_rata_and_follow_stake_changes() {
+    let (env, c, admin, u1, u2, _u3) = setup_auth();
+    let (reward, source) = rewards_setup(&env, &c, &admin);
+    fund(&env, &c, &u1, 1_000);
+    fund(&env, &c, &u2, 1_000);
+    c.stake(&u1, &100i128);
+    c.stake(&u2, &300i128);
+
+    distribute(&env, &c, &source, &reward, 400);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(100));
+    assert_eq!(c.pending_rewards(&u2).get(reward.clone()), Some(300));
+
+    // u1 triples their stake: later rewards split 300/300, earlier ones are kept
+    c.stake(&u1, &200i128);
+    distribute(&env, &c, &source, &reward, 600);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(400));
+    assert_eq!(c.pending_rewards(&u2).get(reward.clone()), Some(600));
+
+    // u2 leaves entirely: keeps what was earned, earns nothing more
+    c.unstake(&u2, &300i128);
+    distribute(&env, &c, &source, &reward, 300);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(700));
+    assert_eq!(c.pending_rewards(&u2).get(reward.clone()), Some(600));
+
+    assert_eq!(c.claim_rewards(&u1).get(reward.clone()), Some(700));
+    assert_eq!(token::Client::new(&env, &reward).balance(&u1), 700);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(0));
+    assert_eq!(c.claim_rewards(&u2).get(reward.clone()), Some(600));
+    assert_eq!(token::Client::new(&env, &reward).balance(&c.address), 0);
+}
+
+#[test]
+fn rewards_with_nothing_staked_roll_into_next_distribution() {
+    let (env, c, admin, u1, _u2, _u3) = setup_auth();
+    let (reward, source) = rewards_setup(&env, &c, &admin);
+    distribute(&env, &c, &source, &reward, 50);
+
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &10i128);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(0));
+    distribute(&env, &c, &source, &reward, 25);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(75));
+}
+
+#[test]
+fn reward_division_remainder_carries_into_next_distribution() {
+    let (env, c, admin, u1, _u2, _u3) = setup_auth();
+    let (reward, source) = rewards_setup(&env, &c, &admin);
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &3i128);
+    // 1 / 3 per unit doesn't divide evenly; the leftover joins the next notification
+    distribute(&env, &c, &source, &reward, 1);
+    distribute(&env, &c, &source, &reward, 2);
+    assert_eq!(c.pending_rewards(&u1).get(reward.clone()), Some(3));
+}
+
+#[test]
+fn rewards_are_tracked_per_token() {
+    let (env, c, admin, u1, _u2, _u3) = setup_auth();
+    let (reward, source) = rewards_setup(&env, &c, &admin);
+    let other = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    fund(&env, &c, &u1, 1_000);
+    c.stake(&u1, &10i128);
+    distribute(&env, &c, &source, &reward, 5);
+    distribute(&env, &c, &source, &other, 7);
+
+    let paid = c.claim_rewards(&u1);
+    assert_eq!(paid.get(reward), Some(5));
+    assert_eq!(paid.get(other), Some(7));
+}
+
+#[test]
+#[should_panic] // only the registered reward source may notify
+fn notify_reward_from_stranger_panics() {
+    let (env, c, admin, _u1, _u2, _u3) = setup_auth();
+    let (reward, _source) = rewards_setup(&env, &c, &admin);
+    let stranger = <Address as TestAddress>::generate(&env);
+    c.notify_reward(&stranger, &reward, &10i128); // -> NotRewardSource
+}
@@ -3,20 +3,34 @@
 pub mod reflector;
 
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype, Address, Env, String, Vec,
+    contract, contracterror, contractimpl, contracttype, symbol_short, Address, Env, String, Vec,
 };
-use crate::reflector::{ReflectorClient, Asset as ReflectorAsset}; 
+use oracle_interface::OracleInterface;
+use crate::reflector::{ReflectorClient, Asset as ReflectorAsset};
 
 const HISTORY_CAP: u32 = 256;
+const HISTORY_TTL: u32 = 518_400;           // ~30 days of ledgers
+const HISTORY_TTL_THRESHOLD: u32 = 259_200; // extend once less than ~15 days remain
+const CANDLE_CAP: u32 = 128;        // candles kept per (asset, resolution)
+const MAX_RESOLUTIONS: u32 = 6;
+const FEED_ROUND_TTL: u32 = 17_280; // ~1 day of ledgers; open feed rounds are short-lived
 
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     Admin,
-    Feeder,
+    Feeders,               // Vec<Address> allowed to push / pull prices
+    Aggregation,           // quorum & outlier settings for multi-feeder pushes
+    FeedRound(String, u64), // (asset code, round index) -> submissions awaiting quorum (temporary)
+    Outliers(Address),     // feeder -> number of submissions flagged as outliers
+    Halted(String),        // asset code -> PricePoint that tripped the circuit breaker
     Reflector,             // Address of external Reflector contract
     AssetCfg(String),      // per-asset config (keyed by a simple asset code string)
-    History(String),       // per-asset price history (same key as AssetCfg)
+    HistoryHead(String),        // per-asset ring buffer position (persistent)
+    HistorySlot(String, u32),   // (asset code, slot) -> PricePoint (persistent)
+    Resolutions,                // Vec<u64> candle resolutions in seconds
+    CandleHead(String, u64),    // (asset code, resolution) -> ring buffer position (persistent)
+    Candle(String, u64, u32),   // (asset code, resolution, slot) -> Candle (persistent)
 }
 
 #[derive(Clone)]
@@ -24,23 +38,74 @@ pub enum DataKey {
 pub struct AssetCfg {
     pub decimals: u32,     // default output decimals
     pub max_age_secs: u64, // staleness window
+    pub source: FeedSource,          // where `pull_from_reflector` reads this asset from
+    pub reflector: Option<Address>,  // per-asset Reflector contract (defaults to DataKey::Reflector)
+    pub max_move_bps: u32,     // largest accepted move vs the last point before halting (0 = off)
+    pub max_skew_secs: u64,    // how far a pushed point's `ts` may be from ledger time, either way
 }
 
+const DEFAULT_MAX_SKEW_SECS: u64 = 60;
+
+/// How an asset's price reaches the adapter.
 #[derive(Clone)]
 #[contracttype]
-pub struct PricePoint {
-    pub price: i128,   // scaled by `decimals`
-    pub decimals: u32, // redundancy for safety
-    pub ts: u64,       // ledger timestamp (seconds)
+pub enum FeedSource {
+    Manual,                                         // pushed by the feeder only
+    Reflector(ReflectorAsset),                      // pulled from Reflector's quote for this asset
+    ReflectorCross(ReflectorAsset, ReflectorAsset), // (base, quote) cross price, e.g. KALE/XLM
+}
+
+/// How feeder submissions are aggregated before a price is committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Aggregation {
+    pub quorum: u32,            // submissions needed to commit a round; 1 commits every push
+    pub round_secs: u64,        // submissions whose `ts` share a `round_secs` bucket are one round
+    pub max_deviation_bps: u32, // submissions further than this from the median are outliers (0 = off)
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Submission {
+    pub feeder: Address,
+    pub price: i128, // scaled by the asset's configured decimals
+    pub ts: u64,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct FeedRound {
+    pub submissions: Vec<Submission>,
+    pub committed: bool,
+}
+
+/// Position of an asset's history ring buffer: the `len` newest points end just before `head`.
+#[derive(Clone)]
+#[contracttype]
+pub struct HistoryHead {
+    pub head: u32, // slot the next point is written to
+    pub len: u32,  // points stored, at most HISTORY_CAP
+}
+
+/// OHLC of the accepted prices in `[start, start + resolution)`, in the asset's decimals.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Candle {
+    pub start: u64,
+    pub open: i128,
+    pub high: i128,
+    pub low: i128,
+    pub close: i128,
+    pub decimals: u32,
+    pub count: u32, // prices folded into this candle
 }
 
-// Asset representation used only for the Reflector cross-call.
-// NOTE: Variant with named fields is not supported by #[contracttype]; use tuple variant.
 #[derive(Clone)]
 #[contracttype]
-pub enum Asset {
-    Other(String),
-    Stellar(String, Address), // (code, issuer)
+pub struct PricePoint {
+    pub price: i128,   // scaled by `decimals`
+    pub decimals: u32, // redundancy for safety
+    pub ts: u64,       // ledger timestamp (seconds)
 }
 
 #[contracterror]
@@ -56,20 +121,73 @@ pub enum Err {
     BadDecimals = 8,
     MathOverflow = 9,
     ReflectorNotSet = 10,
+    NoReflectorSource = 11,
+    BadAggregation = 12,
+    AlreadySubmitted = 13,
+    RoundClosed = 14,
+    BadPrice = 15,
+    BadTimestamp = 16,
+    Halted = 17,
+    InsufficientCoverage = 18,
+    BadResolution = 19,
 }
 
-// ------------------------------- Reflector Client -------------------------------
+// ------------------------------- Helpers -------------------------------
 
-// #[soroban_sdk::contractclient(name = "ReflectorClient")]
-// #[soroban_sdk::contractclient(name = "ReflectorAsset")]
-pub trait ReflectorIface {
-    fn lastprice(e: Env, asset: Asset) -> (i128, u32, u64);
+fn panic_with(e: &Env, err: Err) -> ! {
+    soroban_sdk::panic_with_error!(e, err)
 }
 
-// ------------------------------- Helpers -------------------------------
+/// Check `caller` is the stored admin and that the call carries its signature.
+fn require_admin(e: &Env, caller: &Address) {
+    let Some(stored_admin) = e.storage().instance().get::<_, Address>(&DataKey::Admin) else {
+        panic_with(e, Err::NotInitialized);
+    };
+    if *caller != stored_admin {
+        panic_with(e, Err::NotAdmin);
+    }
+    caller.require_auth();
+}
+
+/// Check `caller` is one of the stored feeders and that the call carries its signature.
+fn require_feeder(e: &Env, caller: &Address) {
+    let Some(feeders) = e.storage().instance().get::<_, Vec<Address>>(&DataKey::Feeders) else {
+        panic_with(e, Err::NotInitialized);
+    };
+    if !feeders.contains(caller) {
+        panic_with(e, Err::NotFeeder);
+    }
+    caller.require_auth();
+}
+
+fn read_aggregation(e: &Env) -> Aggregation {
+    e.storage()
+        .instance()
+        .get(&DataKey::Aggregation)
+        .unwrap_or(Aggregation { quorum: 1, round_secs: 60, max_deviation_bps: 0 })
+}
 
-fn panic_with(err: Err) -> ! {
-    soroban_sdk::panic_with_error!(&Env::default(), err)
+/// Median of the submitted prices; the lower middle one for an even count, so the result is
+/// always a price some feeder actually submitted rather than a blend of two.
+fn median(e: &Env, subs: &Vec<Submission>) -> i128 {
+    let mut sorted: Vec<i128> = Vec::new(e);
+    for s in subs.iter() {
+        let mut i = 0;
+        while i < sorted.len() && sorted.get_unchecked(i) <= s.price {
+            i += 1;
+        }
+        sorted.insert(i, s.price);
+    }
+    sorted.get_unchecked((sorted.len() - 1) / 2)
+}
+
+/// Whether `price` is more than `max_bps` away from `reference` (never, when `max_bps` is 0).
+fn deviates(price: i128, reference: i128, max_bps: u32) -> bool {
+    if max_bps == 0 {
+        return false;
+    }
+    let diff = (price - reference).unsigned_abs();
+    diff.saturating_mul(10_000) > reference.unsigned_abs().saturating_mul(max_bps as u128)
 }
 
 fn now(e: &Env) -> u64 {
@@ -102,15 +220,20 @@ fn pow10_i128(n: u32) -> Result<i128, Err> {
     Ok(acc)
 }
 
-fn asset_code_str(asset: &Asset, e: &Env) -> String {
-    match asset {
-        Asset::Other(sym) => sym.clone(),
-        Asset::Stellar(code, _issuer) => {
-            // NOTE: For uniqueness you may want "STELLAR:CODE:ISSUER".
-            // To avoid String concatenation APIs, we keep it minimal here and just use CODE.
-            code.clone()
-        }
-    }
+fn reflector_for<'a>(e: &'a Env, cfg: &AssetCfg) -> ReflectorClient<'a> {
+    let reflector = cfg
+        .reflector
+        .clone()
+        .or_else(|| e.storage().instance().get::<_, Address>(&DataKey::Reflector))
+        .unwrap_or_else(|| panic_with(e, Err::ReflectorNotSet));
+    ReflectorClient::new(e, &reflector)
+}
+
+fn read_cfg(e: &Env, code: &String) -> AssetCfg {
+    e.storage()
+        .instance()
+        .get(&DataKey::AssetCfg(code.clone()))
+        .unwrap_or_else(|| panic_with(e, Err::UnknownAsset))
 }
 
 // ------------------------------- Contract -------------------------------
@@ -124,185 +247,577 @@ impl OracleAdapter {
 
     pub fn init(e: Env, admin: Address, reflector: Address) {
         if e.storage().instance().has(&DataKey::Admin) {
-            panic_with(Err::AlreadyInitialized);
-        }
-        if !cfg!(test) {
-            admin.require_auth();
+            panic_with(&e, Err::AlreadyInitialized);
         }
-
-        // admin.require_auth();
+        admin.require_auth();
         e.storage().instance().set(&DataKey::Admin, &admin);
-        e.storage().instance().set(&DataKey::Feeder, &admin); // default feeder = admin
+        e.storage().instance().set(&DataKey::Feeders, &Vec::from_array(&e, [admin.clone()])); // default feeder = admin
         e.storage().instance().set(&DataKey::Reflector, &reflector);
     }
 
+    /// Replace the feeder set with a single feeder whose pushes commit directly (quorum 1).
     pub fn set_feeder(e: Env, caller: Address, feeder: Address) {
-        let Some(stored_admin) = e.storage().instance().get::<_, Address>(&DataKey::Admin) else {
-            panic_with(Err::NotInitialized);
-        };
-        if caller != stored_admin {
-            panic_with(Err::NotAdmin);
+        let feeders = Vec::from_array(&e, [feeder]);
+        Self::set_feeders(e, caller, feeders, 1);
+    }
+
+    /// Replace the feeder set; a round commits once `quorum` distinct feeders submitted to it.
+    /// Feeders must be distinct, and a set of more than one needs an odd quorum of at least 3,
+    /// so the committed median always has honest submissions on both sides of it.
+    pub fn set_feeders(e: Env, caller: Address, feeders: Vec<Address>, quorum: u32) {
+        require_admin(&e, &caller);
+        for i in 1..feeders.len() {
+            if feeders.slice(..i).contains(feeders.get_unchecked(i)) {
+                panic_with(&e, Err::BadAggregation);
+            }
+        }
+        let bad_quorum = if feeders.len() > 1 { quorum < 3 || quorum.is_multiple_of(2) } else { quorum != 1 };
+        if bad_quorum || quorum > feeders.len() {
+            panic_with(&e, Err::BadAggregation);
         }
-        e.storage().instance().set(&DataKey::Feeder, &feeder);
+        let agg = Aggregation { quorum, ..read_aggregation(&e) };
+        e.storage().instance().set(&DataKey::Feeders, &feeders);
+        e.storage().instance().set(&DataKey::Aggregation, &agg);
     }
 
-    pub fn set_reflector(e: Env, caller: Address, reflector: Address) {
-        let Some(stored_admin) = e.storage().instance().get::<_, Address>(&DataKey::Admin) else {
-            panic_with(Err::NotInitialized);
-        };
-        if caller != stored_admin {
-            panic_with(Err::NotAdmin);
+    /// Set how submission timestamps are bucketed into rounds and the outlier threshold.
+    pub fn set_aggregation(e: Env, caller: Address, round_secs: u64, max_deviation_bps: u32) {
+        require_admin(&e, &caller);
+        if round_secs == 0 {
+            panic_with(&e, Err::BadAggregation);
         }
+        let agg = Aggregation { round_secs, max_deviation_bps, ..read_aggregation(&e) };
+        e.storage().instance().set(&DataKey::Aggregation, &agg);
+    }
+
+    /// Set the candle resolutions (seconds) maintained for every asset, e.g. `[60, 300, 3600]`.
+    pub fn set_candle_resolutions(e: Env, caller: Address, resolutions: Vec<u64>) {
+        require_admin(&e, &caller);
+        if resolutions.len() > MAX_RESOLUTIONS || resolutions.iter().any(|r| r == 0) {
+            panic_with(&e, Err::BadResolution);
+        }
+        e.storage().instance().set(&DataKey::Resolutions, &resolutions);
+    }
+
+    pub fn get_candle_resolutions(e: Env) -> Vec<u64> {
+        read_resolutions(&e)
+    }
+
+    pub fn get_feeders(e: Env) -> Vec<Address> {
+        e.storage().instance().get(&DataKey::Feeders).unwrap_or(Vec::new(&e))
+    }
+
+    pub fn get_aggregation(e: Env) -> Aggregation {
+        read_aggregation(&e)
+    }
+
+    /// Number of `feeder`'s submissions that were flagged as outliers.
+    pub fn outlier_count(e: Env, feeder: Address) -> u32 {
+        e.storage().instance().get(&DataKey::Outliers(feeder)).unwrap_or(0)
+    }
+
+    pub fn set_reflector(e: Env, caller: Address, reflector: Address) {
+        require_admin(&e, &caller);
         e.storage().instance().set(&DataKey::Reflector, &reflector);
     }
 
     pub fn upsert_asset(e: Env, caller: Address, asset_code: String, decimals: u32, max_age_secs: u64) {
-        let Some(stored_admin) = e.storage().instance().get::<_, Address>(&DataKey::Admin) else {
-            panic_with(Err::NotInitialized);
-        };
-        if caller != stored_admin {
-            panic_with(Err::NotAdmin);
-        }
+        require_admin(&e, &caller);
         if decimals > 38 {
-            panic_with(Err::BadDecimals);
+            panic_with(&e, Err::BadDecimals);
         }
-        let cfg = AssetCfg { decimals, max_age_secs };
-        e.storage().instance().set(&DataKey::AssetCfg(asset_code), &cfg);
+        let key = DataKey::AssetCfg(asset_code);
+        let cfg = match e.storage().instance().get::<_, AssetCfg>(&key) {
+            Some(prev) => AssetCfg { decimals, max_age_secs, ..prev },
+            None => AssetCfg {
+                decimals,
+                max_age_secs,
+                source: FeedSource::Manual,
+                reflector: None,
+                max_move_bps: 0,
+                max_skew_secs: DEFAULT_MAX_SKEW_SECS,
+            },
+        };
+        e.storage().instance().set(&key, &cfg);
     }
 
-    // --------------------------- Feed / Pull ---------------------------
+    /// Map `asset_code` to the Reflector asset it is pulled from, optionally on its own
+    /// Reflector contract (e.g. the FX or CEX feed) instead of the default one.
+    pub fn set_reflector_source(e: Env, caller: Address, asset_code: String, source: ReflectorAsset, reflector: Option<Address>) {
+        require_admin(&e, &caller);
+        let key = DataKey::AssetCfg(asset_code.clone());
+        let mut cfg = read_cfg(&e, &asset_code);
+        cfg.source = FeedSource::Reflector(source);
+        cfg.reflector = reflector;
+        e.storage().instance().set(&key, &cfg);
+    }
 
-    pub fn pull_from_reflector(e: Env, caller: Address, asset: Asset) -> PricePoint {
-        let Some(stored_feeder) = e.storage().instance().get::<_, Address>(&DataKey::Feeder) else {
-            panic_with(Err::NotInitialized);
+    /// Make `asset_code` a pair priced as `base` in units of `quote` from Reflector's cross
+    /// rates, so rounds can run on arbitrary pairs (KALE/XLM, BTC/ETH, ...).
+    pub fn set_reflector_pair(e: Env, caller: Address, asset_code: String, base: ReflectorAsset, quote: ReflectorAsset, reflector: Option<Address>) {
+        require_admin(&e, &caller);
+        let key = DataKey::AssetCfg(asset_code.clone());
+        let mut cfg = read_cfg(&e, &asset_code);
+        cfg.source = FeedSource::ReflectorCross(base, quote);
+        cfg.reflector = reflector;
+        e.storage().instance().set(&key, &cfg);
+    }
+
+    /// Configure `asset_code`'s circuit breaker: the largest move vs the last accepted point
+    /// (`0` disables it) and the clock skew tolerated on pushed timestamps. Points stamped
+    /// ahead of ledger time are recorded at ledger time; pushes stamped further behind it
+    /// are rejected, so a feeder can't backdate a price into a window that already closed.
+    pub fn set_guardrails(e: Env, caller: Address, asset_code: String, max_move_bps: u32, max_skew_secs: u64) {
+        require_admin(&e, &caller);
+        let key = DataKey::AssetCfg(asset_code.clone());
+        let mut cfg = read_cfg(&e, &asset_code);
+        cfg.max_move_bps = max_move_bps;
+        cfg.max_skew_secs = max_skew_secs;
+        e.storage().instance().set(&key, &cfg);
+    }
+
+    /// Resume a halted asset. With `accept` the point that tripped the breaker becomes the
+    /// latest price; otherwise it is dropped and the last accepted point stays current.
+    /// Pushes are rejected while an asset is halted.
+    pub fn acknowledge_halt(e: Env, caller: Address, asset_code: String, accept: bool) {
+        require_admin(&e, &caller);
+        let key = DataKey::Halted(asset_code.clone());
+        let Some(tripped) = e.storage().instance().get::<_, PricePoint>(&key) else {
+            return;
         };
-        if caller != stored_feeder {
-            panic_with(Err::NotFeeder);
+        e.storage().instance().remove(&key);
+        if accept {
+            append_history(&e, &asset_code, tripped);
         }
-        let Some(reflector) = e.storage().instance().get::<_, Address>(&DataKey::Reflector) else {
-            panic_with(Err::ReflectorNotSet);
-        };
-        let client = ReflectorClient::new(&e, &reflector);
-        let stellar_token_address = "CBHIQPUXLFLC5O44ZJVUTCL5LMZFLVGU5DEIGSYKBSAPFMOGTKOQEPFM"; // BTCLN
-        let ticker = ReflectorAsset::Stellar(Address::from_str(&e, &stellar_token_address));
-        // Fetch the most recent price record for it
-        // let (price, dec, ts) = client.lastprice(&ticker); 
+    }
 
-        let recent = client.lastprice(&ticker);
-        // Check the result
-        if recent.is_none() {
-            panic_with(Err::UnknownAsset);
-        }
-        // Retrieve the price itself
-        let price = recent.clone().unwrap().price;
-        let ts = recent.unwrap().timestamp;
+    /// The point that tripped `asset_code`'s circuit breaker, if it is halted.
+    pub fn get_halt(e: Env, asset_code: String) -> Option<PricePoint> {
+        e.storage().instance().get(&DataKey::Halted(asset_code))
+    }
+
+    // --------------------------- Feed / Pull ---------------------------
 
-        // Do not forget for price precision, get decimals from the oracle
-        // (this value can be also hardcoded once the price feed has been
-        // selected because decimals never change in live oracles)
+    pub fn pull_from_reflector(e: Env, caller: Address, asset_code: String) -> PricePoint {
+        require_feeder(&e, &caller);
+        let cfg = read_cfg(&e, &asset_code);
+        let client = reflector_for(&e, &cfg);
+
+        // Fetch the most recent price record for it
+        let recent = match cfg.source {
+            FeedSource::Reflector(asset) => client.lastprice(&asset),
+            FeedSource::ReflectorCross(base, quote) => client.x_last_price(&base, &quote),
+            FeedSource::Manual => panic_with(&e, Err::NoReflectorSource),
+        };
+        let Some(recent) = recent else {
+            panic_with(&e, Err::NoHistory);
+        };
+        // Reflector decimals are fixed per feed but may differ between feeds
         let dec = client.decimals();
 
-        
-        let pp = PricePoint { price, decimals: dec, ts };
-        let code = asset_code_str(&asset, &e);
-        Self::push_point(&e, &code, pp.clone());
+        let pp = PricePoint { price: recent.price, decimals: dec, ts: recent.timestamp };
+        Self::push_point(&e, &asset_code, pp.clone());
         pp
     }
 
+    /// Submit a price. With a quorum of 1 it is committed as is; otherwise it joins the feed
+    /// round for `ts` and the round's median is committed once `quorum` feeders submitted.
+    /// `ts` may trail ledger time by at most the asset's `max_skew_secs`.
     pub fn push_price(e: Env, caller: Address, asset_code: String, price: i128, decimals: u32, ts: u64) {
-        let Some(stored_feeder) = e.storage().instance().get::<_, Address>(&DataKey::Feeder) else {
-            panic_with(Err::NotInitialized);
-        };
-        if caller != stored_feeder {
-            panic_with(Err::NotFeeder);
+        require_feeder(&e, &caller);
+        let cfg = read_cfg(&e, &asset_code);
+        // rounds read prices as of past timestamps, so a backdated push could pick a settled
+        // round's outcome after the fact
+        if ts.saturating_add(cfg.max_skew_secs) < now(&e) {
+            panic_with(&e, Err::BadTimestamp);
+        }
+        let agg = read_aggregation(&e);
+        if agg.quorum <= 1 {
+            let pp = PricePoint { price, decimals, ts };
+            Self::push_point(&e, &asset_code, pp);
+            return;
+        }
+
+        // normalize so submissions with different decimals are comparable
+        let price = rescale_price(price, decimals, cfg.decimals).unwrap_or_else(|err| panic_with(&e, err));
+        let round = ts / agg.round_secs;
+        let key = DataKey::FeedRound(asset_code.clone(), round);
+        let mut fr = e
+            .storage()
+            .temporary()
+            .get::<_, FeedRound>(&key)
+            .unwrap_or(FeedRound { submissions: Vec::new(&e), committed: false });
+        if fr.committed {
+            panic_with(&e, Err::RoundClosed);
+        }
+        if fr.submissions.iter().any(|s| s.feeder == caller) {
+            panic_with(&e, Err::AlreadySubmitted);
+        }
+        fr.submissions.push_back(Submission { feeder: caller, price, ts });
+
+        if fr.submissions.len() >= agg.quorum {
+            let med = median(&e, &fr.submissions);
+            let mut newest_ts: u64 = 0;
+            for s in fr.submissions.iter() {
+                if s.ts > newest_ts { newest_ts = s.ts; }
+                if deviates(s.price, med, agg.max_deviation_bps) {
+                    let okey = DataKey::Outliers(s.feeder.clone());
+                    let n: u32 = e.storage().instance().get(&okey).unwrap_or(0);
+                    e.storage().instance().set(&okey, &(n + 1));
+                    // submission further than max_deviation_bps from the committed median
+                    e.events().publish((symbol_short!("outlier"), asset_code.clone()), (s.feeder, s.price, med));
+                }
+            }
+            Self::push_point(&e, &asset_code, PricePoint { price: med, decimals: cfg.decimals, ts: newest_ts });
+            e.events().publish((symbol_short!("median"), asset_code.clone()), (round, med, fr.submissions.len()));
+            fr.committed = true;
+        }
+        e.storage().temporary().set(&key, &fr);
+        e.storage().temporary().extend_ttl(&key, FEED_ROUND_TTL, FEED_ROUND_TTL);
+    }
+
+    /// Run `pp` through the asset's guardrails and append it to history. A move beyond
+    /// `max_move_bps` halts the asset instead, keeping the point aside for the admin.
+    fn push_point(e: &Env, code: &String, mut pp: PricePoint) {
+        let cfg = read_cfg(e, code);
+        // the feed is frozen until the admin resolves the tripped point, so it can't be
+        // overtaken by newer points and end up out of order when accepted
+        require_not_halted(e, code);
+        if pp.price <= 0 {
+            panic_with(e, Err::BadPrice);
+        }
+        let now_ts = now(e);
+        if pp.ts > now_ts.saturating_add(cfg.max_skew_secs) {
+            panic_with(e, Err::BadTimestamp);
         }
-        let pp = PricePoint { price, decimals, ts };
-        Self::push_point(&e, &asset_code, pp);
-    }
-
-    fn push_point(e: &Env, code: &String, pp: PricePoint) {
-        let key = DataKey::History(code.clone());
-        let mut hist: Vec<PricePoint> = e.storage().instance().get(&key).unwrap_or(Vec::new(e));
-        hist.push_back(pp);
-        while hist.len() > HISTORY_CAP {
-            // pop front by rebuilding without index 0
-            let mut new_hist: Vec<PricePoint> = Vec::new(e);
-            let n = hist.len();
-            let mut i: u32 = 1;
-            while i < n {
-                new_hist.push_back(hist.get_unchecked(i));
-                i += 1;
+        // clock skew is tolerated but a point never takes effect before ledger time reaches it
+        pp.ts = pp.ts.min(now_ts);
+        if let Some(last) = latest_point(e, code) {
+            if pp.ts < last.ts {
+                panic_with(e, Err::BadTimestamp);
+            }
+            let last_price = rescale_price(last.price, last.decimals, pp.decimals).unwrap_or_else(|err| panic_with(e, err));
+            if deviates(pp.price, last_price, cfg.max_move_bps) {
+                e.storage().instance().set(&DataKey::Halted(code.clone()), &pp);
+                e.events().publish((symbol_short!("halted"), code.clone()), (last_price, pp.price, pp.decimals));
+                return;
             }
-            hist = new_hist;
         }
-        e.storage().instance().set(&key, &hist);
+        append_history(e, code, pp);
     }
 
     // --------------------------- Getters ---------------------------
 
-    pub fn get_spot(e: Env, asset_code: String, out_decimals: u32) -> (i128, u32, u64) {
-        let cfg: AssetCfg = e
-            .storage()
-            .instance()
-            .get(&DataKey::AssetCfg(asset_code.clone()))
-            .unwrap_or_else(|| panic_with(Err::UnknownAsset));
-        let (pp, _) = latest_point(&e, &asset_code).unwrap_or_else(|| panic_with(Err::NoHistory));
+    /// Up to `count` most recent `resolution` candles for `asset_code`, oldest first.
+    pub fn get_candles(e: Env, asset_code: String, resolution: u64, count: u32) -> Vec<Candle> {
+        read_cfg(&e, &asset_code);
+        if !read_resolutions(&e).contains(resolution) {
+            panic_with(&e, Err::BadResolution);
+        }
+        let head = read_candle_head(&e, &asset_code, resolution);
+        let k = if count > head.len { head.len } else { count };
+        let mut out = Vec::new(&e);
+        let mut i = head.len - k;
+        while i < head.len {
+            out.push_back(candle_at(&e, &asset_code, resolution, &head, i));
+            i += 1;
+        }
+        out
+    }
+
+    /// Reflector's own TWAP over its last `records` updates for the asset or pair mapped to
+    /// `asset_code` (`twap` / `x_twap`), rescaled to `out_decimals`.
+    pub fn get_reflector_twap(e: Env, asset_code: String, records: u32, out_decimals: u32) -> (i128, u32, u64) {
+        if records == 0 {
+            panic_with(&e, Err::NoHistory);
+        }
+        let cfg = read_cfg(&e, &asset_code);
+        let client = reflector_for(&e, &cfg);
+        let twap = match cfg.source {
+            FeedSource::Reflector(asset) => client.twap(&asset, &records),
+            FeedSource::ReflectorCross(base, quote) => client.x_twap(&base, &quote, &records),
+            FeedSource::Manual => panic_with(&e, Err::NoReflectorSource),
+        };
+        let Some(twap) = twap else {
+            panic_with(&e, Err::NoHistory);
+        };
+        let ts = client.last_timestamp();
+        let now_ts = now(&e);
+        if now_ts < ts || (now_ts - ts) > cfg.max_age_secs {
+            panic_with(&e, Err::StalePrice);
+        }
+        let price = rescale_price(twap, client.decimals(), out_decimals).unwrap_or_else(|err| panic_with(&e, err));
+        (price, out_decimals, ts)
+    }
+}
+
+// --------------------------- Price interface ---------------------------
+
+#[contractimpl]
+impl OracleInterface for OracleAdapter {
+    fn get_spot(e: Env, asset_code: String, out_decimals: u32) -> (i128, u32, u64) {
+        let cfg = read_cfg(&e, &asset_code);
+        require_not_halted(&e, &asset_code);
+        let pp = latest_point(&e, &asset_code).unwrap_or_else(|| panic_with(&e, Err::NoHistory));
         let now_ts = now(&e);
         if now_ts < pp.ts || (now_ts - pp.ts) > cfg.max_age_secs {
-            panic_with(Err::StalePrice);
+            panic_with(&e, Err::StalePrice);
         }
-        let price = rescale_price(pp.price, pp.decimals, out_decimals).unwrap_or_else(|e| panic_with(e));
+        let price = rescale_price(pp.price, pp.decimals, out_decimals).unwrap_or_else(|err| panic_with(&e, err));
         (price, out_decimals, pp.ts)
     }
 
-    pub fn get_twap(e: Env, asset_code: String, records: u32, out_decimals: u32) -> (i128, u32, u64) {
-        if records == 0 {
-            panic_with(Err::NoHistory);
+    fn get_twap(e: Env, asset_code: String, records: u32, out_decimals: u32) -> (i128, u32, u64) {
+        twap_records(&e, &asset_code, now(&e), records, out_decimals)
+    }
+
+    fn get_twap_at(e: Env, asset_code: String, ts: u64, records: u32, out_decimals: u32) -> (i128, u32, u64) {
+        if ts > now(&e) {
+            panic_with(&e, Err::BadTimestamp);
         }
-        let cfg: AssetCfg = e
-            .storage()
-            .instance()
-            .get(&DataKey::AssetCfg(asset_code.clone()))
-            .unwrap_or_else(|| panic_with(Err::UnknownAsset));
+        twap_records(&e, &asset_code, ts, records, out_decimals)
+    }
 
-        let hist: Vec<PricePoint> = e
-            .storage()
-            .instance()
-            .get(&DataKey::History(asset_code.clone()))
-            .unwrap_or(Vec::new(&e));
-        let n = hist.len();
-        if n == 0 {
-            panic_with(Err::NoHistory);
+    /// Price in effect at `ts`: the last history point at or before it, else Reflector's
+    /// record for that time (`price` / `x_price`) when history doesn't reach back that far.
+    fn get_price_at(e: Env, asset_code: String, ts: u64, out_decimals: u32) -> (i128, u32, u64) {
+        let cfg = read_cfg(&e, &asset_code);
+        require_not_halted(&e, &asset_code);
+        if ts > now(&e) {
+            panic_with(&e, Err::BadTimestamp);
         }
-        let k = if records > n { n } else { records };
-        let start = n - k;
-
-        let mut sum: i128 = 0;
-        let mut newest_ts: u64 = 0;
-        let mut i = start;
-        while i < n {
-            let pp = hist.get_unchecked(i);
-            sum = sum.checked_add(
-                rescale_price(pp.price, pp.decimals, out_decimals).unwrap_or_else(|e| panic_with(e))
-            ).unwrap_or_else(|| panic_with(Err::MathOverflow));
-            if pp.ts > newest_ts { newest_ts = pp.ts; }
-            i += 1;
+
+        let hist = read_head(&e, &asset_code);
+        // the last point at or before `ts` is the one in effect
+        let n = points_until(&e, &asset_code, &hist, ts);
+        let pp = if n > 0 {
+            history_point(&e, &asset_code, &hist, n - 1)
+        } else {
+            let client = reflector_for(&e, &cfg);
+            let record = match cfg.source {
+                FeedSource::Reflector(asset) => client.price(&asset, &ts),
+                FeedSource::ReflectorCross(base, quote) => client.x_price(&base, &quote, &ts),
+                FeedSource::Manual => panic_with(&e, Err::NoHistory),
+            };
+            let Some(record) = record else {
+                panic_with(&e, Err::NoHistory);
+            };
+            PricePoint { price: record.price, decimals: client.decimals(), ts: record.timestamp }
+        };
+        if ts < pp.ts || (ts - pp.ts) > cfg.max_age_secs {
+            panic_with(&e, Err::StalePrice);
         }
+        let price = rescale_price(pp.price, pp.decimals, out_decimals).unwrap_or_else(|err| panic_with(&e, err));
+        (price, out_decimals, pp.ts)
+    }
 
-        let now_ts = now(&e);
-        if now_ts < newest_ts || (now_ts - newest_ts) > cfg.max_age_secs {
-            panic_with(Err::StalePrice);
+    /// Time-weighted average over the last `window_secs`: each point is weighted by how long
+    /// it was the latest price within the window. History must reach back to the window start.
+    fn get_twap_window(e: Env, asset_code: String, window_secs: u64, out_decimals: u32) -> (i128, u32, u64) {
+        twap_window(&e, &asset_code, now(&e), window_secs, out_decimals)
+    }
+
+    /// Like `get_twap_window`, for the `window_secs` ending at `ts`.
+    fn get_twap_window_at(e: Env, asset_code: String, ts: u64, window_secs: u64, out_decimals: u32) -> (i128, u32, u64) {
+        if ts > now(&e) {
+            panic_with(&e, Err::BadTimestamp);
+        }
+        twap_window(&e, &asset_code, ts, window_secs, out_decimals)
+    }
+}
+
+/// Number of history points stamped at or before `ts`; history is kept in time order.
+fn points_until(e: &Env, code: &String, hist: &HistoryHead, ts: u64) -> u32 {
+    let (mut lo, mut hi) = (0u32, hist.len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if history_point(e, code, hist, mid).ts <= ts { lo = mid + 1; } else { hi = mid; }
+    }
+    lo
+}
+
+/// Mean of the last `records` points stamped at or before `end_ts`; the newest of them
+/// must not be stale at `end_ts`.
+fn twap_records(e: &Env, code: &String, end_ts: u64, records: u32, out_decimals: u32) -> (i128, u32, u64) {
+    if records == 0 {
+        panic_with(e, Err::NoHistory);
+    }
+    let cfg = read_cfg(e, code);
+    require_not_halted(e, code);
+
+    let hist = read_head(e, code);
+    let n = points_until(e, code, &hist, end_ts);
+    if n == 0 {
+        panic_with(e, Err::NoHistory);
+    }
+    let k = if records > n { n } else { records };
+    let start = n - k;
+
+    let mut sum: i128 = 0;
+    let mut newest_ts: u64 = 0;
+    let mut i = start;
+    while i < n {
+        let pp = history_point(e, code, &hist, i);
+        sum = sum.checked_add(
+            rescale_price(pp.price, pp.decimals, out_decimals).unwrap_or_else(|err| panic_with(e, err))
+        ).unwrap_or_else(|| panic_with(e, Err::MathOverflow));
+        if pp.ts > newest_ts { newest_ts = pp.ts; }
+        i += 1;
+    }
+
+    if end_ts - newest_ts > cfg.max_age_secs {
+        panic_with(e, Err::StalePrice);
+    }
+    let avg = sum / (k as i128);
+    (avg, out_decimals, newest_ts)
+}
+
+/// Time-weighted mean over the `window_secs` ending at `end_ts`, counting only points
+/// stamped by then.
+fn twap_window(e: &Env, code: &String, end_ts: u64, window_secs: u64, out_decimals: u32) -> (i128, u32, u64) {
+    if window_secs == 0 {
+        panic_with(e, Err::InsufficientCoverage);
+    }
+    let cfg = read_cfg(e, code);
+    require_not_halted(e, code);
+
+    let hist = read_head(e, code);
+    let n = points_until(e, code, &hist, end_ts);
+    if n == 0 {
+        panic_with(e, Err::NoHistory);
+    }
+    let newest_ts = history_point(e, code, &hist, n - 1).ts;
+    if end_ts - newest_ts > cfg.max_age_secs {
+        panic_with(e, Err::StalePrice);
+    }
+    let start = end_ts.checked_sub(window_secs).unwrap_or_else(|| panic_with(e, Err::InsufficientCoverage));
+
+    // walk back from the newest point; each is in effect until the next one (or `end_ts`)
+    let mut weighted: i128 = 0;
+    let mut end = end_ts;
+    let mut i = n;
+    while i > 0 && end > start {
+        i -= 1;
+        let pp = history_point(e, code, &hist, i);
+        let from = if pp.ts > start { pp.ts } else { start };
+        if pp.ts > start && i == 0 {
+            panic_with(e, Err::InsufficientCoverage);
         }
-        let avg = sum / (k as i128);
-        (avg, out_decimals, newest_ts)
+        let price = rescale_price(pp.price, pp.decimals, out_decimals).unwrap_or_else(|err| panic_with(e, err));
+        weighted = price
+            .checked_mul((end - from) as i128)
+            .and_then(|w| weighted.checked_add(w))
+            .unwrap_or_else(|| panic_with(e, Err::MathOverflow));
+        end = from;
+    }
+    (weighted / window_secs as i128, out_decimals, newest_ts)
+}
+
+fn read_head(e: &Env, code: &String) -> HistoryHead {
+    e.storage()
+        .persistent()
+        .get(&DataKey::HistoryHead(code.clone()))
+        .unwrap_or(HistoryHead { head: 0, len: 0 })
+}
+
+/// The `i`-th oldest stored point (`i < hist.len`).
+fn history_point(e: &Env, code: &String, hist: &HistoryHead, i: u32) -> PricePoint {
+    let slot = (hist.head + HISTORY_CAP - hist.len + i) % HISTORY_CAP;
+    e.storage()
+        .persistent()
+        .get(&DataKey::HistorySlot(code.clone(), slot))
+        .unwrap_or_else(|| panic_with(e, Err::NoHistory))
+}
+
+/// Write `pp` over the oldest slot once the ring is full; O(1) regardless of history size.
+fn append_history(e: &Env, code: &String, pp: PricePoint) {
+    let mut hist = read_head(e, code);
+    let slot_key = DataKey::HistorySlot(code.clone(), hist.head);
+    e.storage().persistent().set(&slot_key, &pp);
+    e.storage().persistent().extend_ttl(&slot_key, HISTORY_TTL_THRESHOLD, HISTORY_TTL);
+    hist.head = (hist.head + 1) % HISTORY_CAP;
+    if hist.len < HISTORY_CAP {
+        hist.len += 1;
+    }
+    let head_key = DataKey::HistoryHead(code.clone());
+    e.storage().persistent().set(&head_key, &hist);
+    e.storage().persistent().extend_ttl(&head_key, HISTORY_TTL_THRESHOLD, HISTORY_TTL);
+    update_candles(e, code, &pp);
+}
+
+fn read_resolutions(e: &Env) -> Vec<u64> {
+    e.storage()
+        .instance()
+        .get(&DataKey::Resolutions)
+        .unwrap_or(Vec::from_array(e, [60, 300, 3_600]))
+}
+
+fn read_candle_head(e: &Env, code: &String, resolution: u64) -> HistoryHead {
+    e.storage()
+        .persistent()
+        .get(&DataKey::CandleHead(code.clone(), resolution))
+        .unwrap_or(HistoryHead { head: 0, len: 0 })
+}
+
+/// The `i`-th oldest stored candle (`i < head.len`).
+fn candle_at(e: &Env, code: &String, resolution: u64, head: &HistoryHead, i: u32) -> Candle {
+    let slot = (head.head + CANDLE_CAP - head.len + i) % CANDLE_CAP;
+    e.storage()
+        .persistent()
+        .get(&DataKey::Candle(code.clone(), resolution, slot))
+        .unwrap_or_else(|| panic_with(e, Err::NoHistory))
+}
+
+/// Fold an accepted point into the current candle of every resolution, opening a new
+/// candle when it falls in a later bucket. Points older than the current candle are skipped.
+fn update_candles(e: &Env, code: &String, pp: &PricePoint) {
+    let cfg = read_cfg(e, code);
+    let price = rescale_price(pp.price, pp.decimals, cfg.decimals).unwrap_or_else(|err| panic_with(e, err));
+    for resolution in read_resolutions(e).iter() {
+        let start = pp.ts - pp.ts % resolution;
+        let mut head = read_candle_head(e, code, resolution);
+        let (slot, candle) = match (head.len > 0).then(|| candle_at(e, code, resolution, &head, head.len - 1)) {
+            Some(c) if c.start == start => {
+                let slot = (head.head + CANDLE_CAP - 1) % CANDLE_CAP;
+                let candle = Candle {
+                    high: c.high.max(price),
+                    low: c.low.min(price),
+                    close: price,
+                    count: c.count + 1,
+                    ..c
+                };
+                (slot, candle)
+            }
+            Some(c) if c.start > start => continue,
+            _ => {
+                let slot = head.head;
+                head.head = (head.head + 1) % CANDLE_CAP;
+                if head.len < CANDLE_CAP {
+                    head.len += 1;
+                }
+                let head_key = DataKey::CandleHead(code.clone(), resolution);
+                e.storage().persistent().set(&head_key, &head);
+                e.storage().persistent().extend_ttl(&head_key, HISTORY_TTL_THRESHOLD, HISTORY_TTL);
+                let candle = Candle { start, open: price, high: price, low: price, close: price, decimals: cfg.decimals, count: 1 };
+                (slot, candle)
+            }
+        };
+        let key = DataKey::Candle(code.clone(), resolution, slot);
+        e.storage().persistent().set(&key, &candle);
+        e.storage().persistent().extend_ttl(&key, HISTORY_TTL_THRESHOLD, HISTORY_TTL);
+    }
+}
+
+fn require_not_halted(e: &Env, code: &String) {
+    if e.storage().instance().has(&DataKey::Halted(code.clone())) {
+        panic_with(e, Err::Halted);
     }
 }
 
 // Helper visible to this module
-fn latest_point(e: &Env, code: &String) -> Option<(PricePoint, u32)> {
-    let hist: Vec<PricePoint> = e.storage().instance().get(&DataKey::History(code.clone()))?;
-    let n = hist.len();
-    if n == 0 { None } else { Some((hist.get_unchecked(n - 1), n)) }
+fn latest_point(e: &Env, code: &String) -> Option<PricePoint> {
+    let hist = read_head(e, code);
+    if hist.len == 0 { None } else { Some(history_point(e, code, &hist, hist.len - 1)) }
 }
 
 
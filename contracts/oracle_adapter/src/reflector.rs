@@ -39,8 +39,8 @@ pub trait Contract {
     // Note: it's safe to remove any methods not used by the consumer contract from this client trait
 }
 
-// Quoted asset definition
-#[contracttype(export = false)]
+// Quoted asset definition (also stored in the adapter's AssetCfg, so it is exported)
+#[contracttype]
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Asset {
     Stellar(Address), // for Stellar Classic and Soroban assets
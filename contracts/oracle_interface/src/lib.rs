@@ -0,0 +1,28 @@
+#![no_std]
+//! Price interface oracle_adapter implements and round_engine calls through the generated
+//! `OracleClient`, so both sides are checked against one signature at compile time.
+
+use soroban_sdk::{contractclient, Env, String};
+
+/// Prices come back as `(price, decimals, ts)`, with `price` scaled by `decimals`
+/// (always the requested `out_decimals`) and `ts` the ledger time of the underlying point.
+#[contractclient(name = "OracleClient")]
+pub trait OracleInterface {
+    /// Latest price of `asset_code`; fails when stale.
+    fn get_spot(e: Env, asset_code: String, out_decimals: u32) -> (i128, u32, u64);
+
+    /// Mean of the last `records` points.
+    fn get_twap(e: Env, asset_code: String, records: u32, out_decimals: u32) -> (i128, u32, u64);
+
+    /// Time-weighted mean over the last `window_secs`.
+    fn get_twap_window(e: Env, asset_code: String, window_secs: u64, out_decimals: u32) -> (i128, u32, u64);
+
+    /// Price in effect at `ts`.
+    fn get_price_at(e: Env, asset_code: String, ts: u64, out_decimals: u32) -> (i128, u32, u64);
+
+    /// Mean of the last `records` points at or before `ts`.
+    fn get_twap_at(e: Env, asset_code: String, ts: u64, records: u32, out_decimals: u32) -> (i128, u32, u64);
+
+    /// Time-weighted mean over the `window_secs` ending at `ts`.
+    fn get_twap_window_at(e: Env, asset_code: String, ts: u64, window_secs: u64, out_decimals: u32) -> (i128, u32, u64);
+}
@@ -1,7 +1,10 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype, symbol_short, vec, Address, Env, String
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contracterror, contractimpl, contracttype, symbol_short, token, vec, Address, Env, String, Symbol,
+    TryFromVal, Val, Vec,
 };
+use oracle_interface::OracleClient;
 
 
 #[derive(Clone)]
@@ -9,11 +12,57 @@ use soroban_sdk::{
 pub enum DataKey {
     Admin,
     OracleId,                  // Contract Address of oracle_adapter
+    Token,                     // Stake token (SAC or any token-interface contract)
+    FeeCfg,                    // Protocol fee settings (creator_hub routing)
+    Treasury,                  // kale_pass_treasury queried for fee discounts
+    HeldFees(Address),         // fee token -> fees the hub rejected, awaiting `sweep_fees`
     NextId,
-    Round(u64),                // Round data
-    Joined(u64, Address),      // anti-double-join marker
+    Round(u64),                // Round data (persistent; temporary once fully paid out)
+    Position(u64, Address),    // participant's Position (persistent; temporary once paid out)
+    IndexLen(RoundIndex),      // number of round ids in an index (persistent)
+    IndexAt(RoundIndex, u32),  // (index, position) -> round id (persistent)
+    ActiveLen(String),         // asset -> number of its Created / Locked rounds (persistent)
+    ActiveAt(String, u32),     // (asset, slot) -> id of one of those rounds (persistent)
+    ActiveSlot(u64),           // round id -> its slot in ActiveAt while active (persistent)
 }
 
+/// Append-only lists of round ids, in creation order.
+#[derive(Clone)]
+#[contracttype]
+pub enum RoundIndex {
+    Creator(Address),
+    Asset(String),
+    Player(Address),           // rounds a user joined
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct RoundEntry {
+    pub id: u64,
+    pub round: Round,
+}
+
+/// One page of a round listing; pass `next_cursor` back to continue, `None` once exhausted.
+#[derive(Clone)]
+#[contracttype]
+pub struct RoundPage {
+    pub rounds: Vec<RoundEntry>,
+    pub next_cursor: Option<u64>,
+}
+
+const MAX_PAGE: u32 = 50;
+/// Rounds examined per listing call when filtering by status, so a page stays cheap even
+/// when few rounds match; the cursor picks up where the scan stopped.
+const MAX_SCAN: u32 = 200;
+
+const DAY_IN_LEDGERS: u32 = 17_280;
+/// Rounds and stakes stay live for a month after they were last touched, then get archived.
+const ROUND_TTL: u32 = 30 * DAY_IN_LEDGERS;
+const ROUND_TTL_THRESHOLD: u32 = ROUND_TTL - DAY_IN_LEDGERS;
+/// Finished rounds with nothing left to pay out, and paid-out positions, stay readable this
+/// long, then expire.
+const GRACE_TTL: u32 = 7 * DAY_IN_LEDGERS;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[contracttype]
 pub enum RoundStatus {
@@ -23,11 +72,39 @@ pub enum RoundStatus {
     Canceled = 3,
 }
 
+/// How a round's lock and settle prices are read from oracle_adapter, always as of `lock_ts` /
+/// `settle_ts` rather than when the keeper calls `lock` / `settle`. That is only safe because
+/// the adapter rejects pushes stamped more than its clock-skew tolerance behind ledger time,
+/// so a window can't be filled in after it closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum SettleMode {
+    Spot,            // point in effect at the timestamp (`get_price_at`)
+    Twap(u32),       // mean of the last N points up to it (`get_twap_at`)
+    TwapWindow(u64), // time-weighted mean over the N seconds ending at it (`get_twap_window_at`)
+}
+
+/// Decimals lock and settle prices are requested in, so the two always compare directly.
+const PRICE_DECIMALS: u32 = 14;
+
+/// What the sides of a round are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum RoundKind {
+    UpDown,           // side 0 = Down, 1 = Up, relative to the lock price
+    Range(Vec<i128>), // ascending bucket edges at PRICE_DECIMALS; side i = [edges[i], edges[i+1])
+}
+
+const MAX_BUCKETS: u32 = 16;
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Round {
     pub creator: Address,
     pub asset: String,
+    pub token: Address,        // stake token snapshotted at creation
+    pub mode: SettleMode,      // price source for lock and settle
+    pub kind: RoundKind,
     pub lock_ts: u64,
     pub settle_ts: u64,
     pub status: RoundStatus,
@@ -35,8 +112,48 @@ pub struct Round {
     pub lock_decimals: u32,
     pub settle_price: Option<i128>,
     pub settle_decimals: u32,
-    pub up_count: u32,
-    pub down_count: u32,
+    pub counts: Vec<u32>,      // participants per side
+    pub pools: Vec<i128>,      // total escrowed per side
+    pub winner: Option<u32>,   // set on settle: winning side, or the side count when nobody
+                               // wins (Up/Down tie, price outside every bucket)
+    pub fee_bps: u32,          // protocol fee rate snapshotted on settle
+    pub fee: i128,             // protocol fee collected from winners' claims so far
+    pub paid: u32,             // positions paid out by `claim` / `refund`
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct FeeCfg {
+    pub hub: Address,          // creator_hub contract receiving the fee
+    pub fee_bps: u32,          // share of the losing pool, 0..=10_000
+    pub platform: Address,
+    pub staker_pool: Address,
+}
+
+/// A user's bet in one round.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Position {
+    pub side: u32,
+    pub amount: i128,          // stake escrowed on join
+    pub claimed: bool,         // paid out, by `claim` or `refund`
+    pub payout: i128,          // amount paid out (stake + winnings - fee, or the refund)
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct PositionEntry {
+    pub id: u64,
+    pub status: RoundStatus,   // status of the round the position is in
+    pub position: Position,
+}
+
+/// One page of `positions_of`; pass `next_cursor` back to continue, `None` once exhausted.
+#[derive(Clone)]
+#[contracttype]
+pub struct PositionPage {
+    pub positions: Vec<PositionEntry>,
+    pub next_cursor: Option<u64>,
 }
 
 #[contracterror]
@@ -51,6 +168,18 @@ pub enum Err {
     AlreadyJoined = 7,
     JoinClosed = 8,
     OracleNotSet = 9,
+    TokenNotSet = 10,
+    BadAmount = 11,
+    NotParticipant = 12,
+    NotWinner = 13,
+    AlreadyClaimed = 14,
+    MathOverflow = 15,
+    NotRefundable = 16,
+    AlreadyRefunded = 17,
+    BadFee = 18,
+    BadMode = 19,
+    BadBuckets = 20,
+    BadSide = 21,
 }
 
 fn read_admin(env: &Env) -> Address {
@@ -70,6 +199,21 @@ fn read_oracle(env: &Env) -> Address {
         .unwrap_or_else(|| Env::panic_with_error(env, Err::OracleNotSet))
 }
 
+fn read_token(env: &Env) -> Address {
+    env.storage()
+        .instance()
+        .get::<_, Address>(&DataKey::Token)
+        .unwrap_or_else(|| Env::panic_with_error(env, Err::TokenNotSet))
+}
+
+fn read_fee_cfg(env: &Env) -> Option<FeeCfg> {
+    env.storage().instance().get::<_, FeeCfg>(&DataKey::FeeCfg)
+}
+
+fn read_treasury(env: &Env) -> Option<Address> {
+    env.storage().instance().get::<_, Address>(&DataKey::Treasury)
+}
+
 fn get_next_id(env: &Env) -> u64 {
     env.storage()
         .instance()
@@ -80,14 +224,345 @@ fn put_next_id(env: &Env, v: u64) {
     env.storage().instance().set(&DataKey::NextId, &v);
 }
 
+fn bump(env: &Env, key: &DataKey) {
+    env.storage().persistent().extend_ttl(key, ROUND_TTL_THRESHOLD, ROUND_TTL);
+}
+
+/// Read a persistent entry and bump it, so entries that are still read never get archived.
+fn read_live<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+    let v = env.storage().persistent().get::<_, V>(key);
+    if v.is_some() { bump(env, key); }
+    v
+}
+
+/// A round still open, or finished within its grace period; `None` once it expired.
+fn find_round(env: &Env, id: u64) -> Option<Round> {
+    let key = DataKey::Round(id);
+    if let Some(r) = env.storage().persistent().get::<_, Round>(&key) {
+        bump(env, &key);
+        return Some(r);
+    }
+    env.storage().temporary().get::<_, Round>(&key)
+}
 fn get_round(env: &Env, id: u64) -> Round {
-    env.storage()
-        .instance()
-        .get::<_, Round>(&DataKey::Round(id))
-        .unwrap_or_else(|| Env::panic_with_error(env, Err::RoundNotFound))
+    find_round(env, id).unwrap_or_else(|| Env::panic_with_error(env, Err::RoundNotFound))
 }
+/// Open rounds are persistent. Once a round is settled or canceled and every position owed
+/// a payout has been paid, it moves to temporary storage and expires after GRACE_TTL.
 fn put_round(env: &Env, id: u64, r: &Round) {
-    env.storage().instance().set(&DataKey::Round(id), r);
+    let key = DataKey::Round(id);
+    if is_closed(r) {
+        env.storage().persistent().remove(&key);
+        env.storage().temporary().set(&key, r);
+        env.storage().temporary().extend_ttl(&key, GRACE_TTL, GRACE_TTL);
+    } else {
+        env.storage().persistent().set(&key, r);
+        bump(env, &key);
+    }
+    env.storage().instance().extend_ttl(ROUND_TTL_THRESHOLD, ROUND_TTL);
+}
+
+/// `who`'s position in round `id`; `None` if they never joined or it expired after payout.
+fn find_position(env: &Env, id: u64, who: &Address) -> Option<Position> {
+    let key = DataKey::Position(id, who.clone());
+    if let Some(pos) = env.storage().persistent().get::<_, Position>(&key) {
+        bump(env, &key);
+        return Some(pos);
+    }
+    env.storage().temporary().get::<_, Position>(&key)
+}
+fn get_position(env: &Env, id: u64, who: &Address) -> Position {
+    find_position(env, id, who).unwrap_or_else(|| Env::panic_with_error(env, Err::NotParticipant))
+}
+/// Open positions are persistent. A paid-out one moves to temporary storage, where it still
+/// reports AlreadyClaimed / AlreadyRefunded for GRACE_TTL, then expires; a later claim fails
+/// with NotParticipant, so it can never be paid twice.
+fn put_position(env: &Env, id: u64, who: &Address, pos: &Position) {
+    let key = DataKey::Position(id, who.clone());
+    if pos.claimed {
+        env.storage().persistent().remove(&key);
+        env.storage().temporary().set(&key, pos);
+        env.storage().temporary().extend_ttl(&key, GRACE_TTL, GRACE_TTL);
+    } else {
+        env.storage().persistent().set(&key, pos);
+        bump(env, &key);
+    }
+}
+
+fn index_push(env: &Env, index: RoundIndex, id: u64) {
+    let len_key = DataKey::IndexLen(index.clone());
+    let n = read_live::<u32>(env, &len_key).unwrap_or(0);
+    let at_key = DataKey::IndexAt(index, n);
+    env.storage().persistent().set(&at_key, &id);
+    bump(env, &at_key);
+    env.storage().persistent().set(&len_key, &(n + 1));
+    bump(env, &len_key);
+}
+
+fn index_len(env: &Env, index: &RoundIndex) -> u64 {
+    read_live::<u32>(env, &DataKey::IndexLen(index.clone())).unwrap_or(0) as u64
+}
+
+fn index_at(env: &Env, index: &RoundIndex, pos: u64) -> u64 {
+    read_live::<u64>(env, &DataKey::IndexAt(index.clone(), pos as u32))
+        .unwrap_or_else(|| Env::panic_with_error(env, Err::RoundNotFound))
+}
+
+fn active_len(env: &Env, asset: &String) -> u64 {
+    read_live::<u32>(env, &DataKey::ActiveLen(asset.clone())).unwrap_or(0) as u64
+}
+
+/// Id of the round in `slot`. Its ActiveSlot entry is bumped along with it: `deactivate`
+/// needs it once the round settles, however long the round stays listed.
+fn active_at(env: &Env, asset: &String, slot: u64) -> u64 {
+    let id = read_live::<u64>(env, &DataKey::ActiveAt(asset.clone(), slot as u32))
+        .unwrap_or_else(|| Env::panic_with_error(env, Err::RoundNotFound));
+    bump(env, &DataKey::ActiveSlot(id));
+    id
+}
+
+/// Add a new round to its asset's active set. Each member is its own entry, so the set can
+/// grow without any single entry (or `settle` / `cancel`) getting more expensive.
+fn activate(env: &Env, asset: &String, id: u64) {
+    let n = active_len(env, asset) as u32;
+    let at_key = DataKey::ActiveAt(asset.clone(), n);
+    env.storage().persistent().set(&at_key, &id);
+    bump(env, &at_key);
+    let slot_key = DataKey::ActiveSlot(id);
+    env.storage().persistent().set(&slot_key, &n);
+    bump(env, &slot_key);
+    let len_key = DataKey::ActiveLen(asset.clone());
+    env.storage().persistent().set(&len_key, &(n + 1));
+    bump(env, &len_key);
+}
+
+/// A round left Created / Locked: drop it from its asset's active set by moving the last
+/// member into its slot.
+fn deactivate(env: &Env, r: &Round, id: u64) {
+    let slot_key = DataKey::ActiveSlot(id);
+    let Some(slot) = env.storage().persistent().get::<_, u32>(&slot_key) else { return };
+    let last = active_len(env, &r.asset) as u32 - 1;
+    if slot != last {
+        let moved = active_at(env, &r.asset, last as u64);
+        let at_key = DataKey::ActiveAt(r.asset.clone(), slot);
+        env.storage().persistent().set(&at_key, &moved);
+        bump(env, &at_key);
+        let moved_key = DataKey::ActiveSlot(moved);
+        env.storage().persistent().set(&moved_key, &slot);
+        bump(env, &moved_key);
+    }
+    env.storage().persistent().remove(&DataKey::ActiveAt(r.asset.clone(), last));
+    env.storage().persistent().remove(&slot_key);
+    let len_key = DataKey::ActiveLen(r.asset.clone());
+    if last == 0 {
+        env.storage().persistent().remove(&len_key);
+    } else {
+        env.storage().persistent().set(&len_key, &last);
+        bump(env, &len_key);
+    }
+}
+
+/// Walk positions `cursor..len` of a listing (`at` maps a position to a round id), keeping
+/// rounds in `status` (any when `None`) until `limit` are found or MAX_SCAN were examined.
+fn page(env: &Env, len: u64, at: impl Fn(u64) -> u64, status: Option<RoundStatus>, cursor: u64, limit: u32) -> RoundPage {
+    env.storage().instance().extend_ttl(ROUND_TTL_THRESHOLD, ROUND_TTL);
+    let limit = limit.min(MAX_PAGE);
+    let mut rounds = Vec::new(env);
+    let mut pos = cursor;
+    let mut scanned = 0;
+    while pos < len && rounds.len() < limit && scanned < MAX_SCAN {
+        let id = at(pos);
+        // expired rounds are skipped
+        if let Some(round) = find_round(env, id) {
+            if status.is_none_or(|s| round.status == s) {
+                rounds.push_back(RoundEntry { id, round });
+            }
+        }
+        pos += 1;
+        scanned += 1;
+    }
+    RoundPage { rounds, next_cursor: if pos < len { Some(pos) } else { None } }
+}
+
+/// Stakes are returned instead of paid out when the round was canceled, nobody won (tie or
+/// a price outside every bucket), or there is no counterparty: the winning side is empty or
+/// holds every stake.
+fn is_refundable(r: &Round) -> bool {
+    match r.status {
+        RoundStatus::Canceled => true,
+        RoundStatus::Settled => match r.winner {
+            Some(w) if w < r.pools.len() => {
+                let (win, lose) = win_lose_pools(r, w);
+                win == 0 || lose == 0
+            }
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+/// Positions still to be paid out when every one owed a payout has been: all of them for a
+/// refundable round, the winning side's otherwise.
+fn owed_count(r: &Round) -> u32 {
+    if is_refundable(r) {
+        r.counts.iter().sum()
+    } else {
+        r.winner.map_or(0, |w| r.counts.get(w).unwrap_or(0))
+    }
+}
+
+/// Settled or canceled with nothing left to pay out.
+fn is_closed(r: &Round) -> bool {
+    matches!(r.status, RoundStatus::Settled | RoundStatus::Canceled) && r.paid >= owed_count(r)
+}
+
+fn side_count(kind: &RoundKind) -> u32 {
+    match kind {
+        RoundKind::UpDown => 2,
+        RoundKind::Range(edges) => edges.len() - 1,
+    }
+}
+
+/// Pool of side `w` and the sum of every other side's pool.
+fn win_lose_pools(r: &Round, w: u32) -> (i128, i128) {
+    let win = r.pools.get_unchecked(w);
+    let total: i128 = r.pools.iter().sum();
+    (win, total - win)
+}
+
+/// Side whose condition the settle price meets, or the side count when none does.
+fn winning_side(r: &Round, lock: i128, settle: i128) -> u32 {
+    match &r.kind {
+        RoundKind::UpDown => {
+            if settle > lock { 1 } else if settle < lock { 0 } else { 2 }
+        }
+        RoundKind::Range(edges) => {
+            let n = edges.len() - 1;
+            (0..n)
+                .find(|&i| edges.get_unchecked(i) <= settle && settle < edges.get_unchecked(i + 1))
+                .unwrap_or(n)
+        }
+    }
+}
+
+/// KALE Pass tier discount for `who`, or 0 when no treasury is configured or it fails to
+/// answer, so a broken treasury never blocks claims.
+fn read_discount_bps(env: &Env, who: &Address) -> u32 {
+    let Some(treasury) = read_treasury(env) else { return 0 };
+    let args = vec![env, who.clone().into_val(env)];
+    match env.try_invoke_contract::<u32, soroban_sdk::Error>(&treasury, &Symbol::new(env, "get_discount_bps"), args) {
+        Ok(Ok(bps)) => bps.min(10_000),
+        _ => 0,
+    }
+}
+
+fn read_held_fees(env: &Env, token: &Address) -> i128 {
+    env.storage().instance().get::<_, i128>(&DataKey::HeldFees(token.clone())).unwrap_or(0)
+}
+
+fn bps_of(env: &Env, amount: i128, bps: u32) -> i128 {
+    amount
+        .checked_mul(bps as i128)
+        .unwrap_or_else(|| Env::panic_with_error(env, Err::MathOverflow))
+        / 10_000
+}
+
+/// Credit a protocol fee collected on round `id` to creator_hub, split between platform,
+/// round creator and stakers there. The hub pulls the fee from the engine during
+/// `credit_fees`, so the tokens only move if the credit succeeds. When the hub rejects it
+/// (engine not a registered fee source, hub trapping), the fee is held here for
+/// `sweep_fees` instead of failing the claim.
+fn route_fee(env: &Env, id: u64, r: &Round, fee: i128) {
+    let Some(cfg) = read_fee_cfg(env) else { return };
+    let engine = env.current_contract_address();
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: r.token.clone(),
+                fn_name: symbol_short!("transfer"),
+                args: vec![env, engine.clone().into_val(env), cfg.hub.clone().into_val(env), fee.into_val(env)],
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+    let args = vec![
+        env,
+        engine.into_val(env),
+        r.token.clone().into_val(env),
+        cfg.platform.into_val(env),
+        r.creator.clone().into_val(env),
+        cfg.staker_pool.into_val(env),
+        fee.into_val(env),
+    ];
+    if let Ok(Ok(())) = env.try_invoke_contract::<(), soroban_sdk::Error>(&cfg.hub, &Symbol::new(env, "credit_fees"), args) {
+        return;
+    }
+    let held = read_held_fees(env, &r.token).saturating_add(fee);
+    env.storage().instance().set(&DataKey::HeldFees(r.token.clone()), &held);
+    env.events().publish((symbol_short!("fee_held"), id), (cfg.hub, r.token.clone(), fee));
+}
+
+/// Snapshot `r.asset`'s price as of `ts` from the oracle according to the round's settlement mode.
+fn read_price(env: &Env, r: &Round, ts: u64) -> (i128, u32) {
+    let oracle = OracleClient::new(env, &read_oracle(env));
+    // (price, decimals, ts)
+    let (p, d, _ts) = match r.mode {
+        SettleMode::Spot => oracle.get_price_at(&r.asset, &ts, &PRICE_DECIMALS),
+        SettleMode::Twap(records) => oracle.get_twap_at(&r.asset, &ts, &records, &PRICE_DECIMALS),
+        SettleMode::TwapWindow(secs) => oracle.get_twap_window_at(&r.asset, &ts, &secs, &PRICE_DECIMALS),
+    };
+    (p, d)
+}
+
+/// Parimutuel winnings: a pro-rata share of the losing pool. Integer division rounds
+/// down; the dust stays in the contract.
+fn winner_share(env: &Env, stake: i128, win_pool: i128, lose_pool: i128) -> i128 {
+    stake
+        .checked_mul(lose_pool)
+        .unwrap_or_else(|| Env::panic_with_error(env, Err::MathOverflow))
+        / win_pool
+}
+
+fn new_round(env: &Env, creator: Address, asset: String, lock_secs: u64, duration_secs: u64, mode: SettleMode, kind: RoundKind) -> u64 {
+    creator.require_auth();
+    let now = env.ledger().timestamp();
+    if lock_secs == 0 || duration_secs == 0 { Env::panic_with_error(env, Err::InvalidTimes); }
+    if mode == SettleMode::Twap(0) || mode == SettleMode::TwapWindow(0) { Env::panic_with_error(env, Err::BadMode); }
+    let token = read_token(env);
+    let n = side_count(&kind) as usize;
+    let lock_ts = now.saturating_add(lock_secs);
+    let settle_ts = lock_ts.saturating_add(duration_secs);
+
+    let id = get_next_id(env);
+    put_next_id(env, id.saturating_add(1));
+
+    let r = Round {
+        creator,
+        asset,
+        token,
+        mode,
+        kind,
+        lock_ts,
+        settle_ts,
+        status: RoundStatus::Created,
+        lock_price: None,
+        lock_decimals: 0,
+        settle_price: None,
+        settle_decimals: 0,
+        counts: Vec::from_slice(env, &[0; MAX_BUCKETS as usize][..n]),
+        pools: Vec::from_slice(env, &[0; MAX_BUCKETS as usize][..n]),
+        winner: None,
+        fee_bps: 0,
+        fee: 0,
+        paid: 0,
+    };
+    put_round(env, id, &r);
+    index_push(env, RoundIndex::Creator(r.creator.clone()), id);
+    index_push(env, RoundIndex::Asset(r.asset.clone()), id);
+    activate(env, &r.asset, id);
+    env.events().publish((symbol_short!("create"), id), (r.lock_ts, r.settle_ts));
+    id
 }
 
 #[contract]
@@ -108,59 +583,83 @@ impl RoundEngine {
         env.events().publish((symbol_short!("oracle"),), oracle_id);
     }
 
+    /// Set the token used for stakes in rounds created from now on.
+    pub fn set_token(env: Env, token: Address) {
+        require_admin(&env);
+        env.storage().instance().set(&DataKey::Token, &token);
+        env.events().publish((symbol_short!("token"),), token);
+    }
+
+    /// Route `fee_bps` of every winner's share of the losing pool to `hub` (creator_hub), split
+    /// between `platform`, the round creator and `staker_pool`. `fee_bps = 0` disables it.
+    /// The rate is fixed per round when it settles. The engine must be registered as a fee
+    /// source on the hub; fees the hub rejects are held for `sweep_fees`.
+    pub fn set_fee_config(env: Env, hub: Address, fee_bps: u32, platform: Address, staker_pool: Address) {
+        require_admin(&env);
+        if fee_bps > 10_000 { Env::panic_with_error(&env, Err::BadFee); }
+        let cfg = FeeCfg { hub, fee_bps, platform, staker_pool };
+        env.storage().instance().set(&DataKey::FeeCfg, &cfg);
+        env.events().publish((symbol_short!("fee_cfg"),), (cfg.hub, fee_bps));
+    }
+
+    /// Set the kale_pass_treasury whose tier discounts reduce the fee charged on claims.
+    pub fn set_treasury(env: Env, treasury: Address) {
+        require_admin(&env);
+        env.storage().instance().set(&DataKey::Treasury, &treasury);
+        env.events().publish((symbol_short!("treasury"),), treasury);
+    }
+
     /// Create a round that opens immediately; players can join until `lock_secs` elapses.
+    /// `creator` must sign: the round is listed under them and earns them the creator fee share.
     /// After lock, a bot/user calls `lock(id)` to snapshot the price; later `settle(id)` finalizes.
-    pub fn create_round(env: Env, creator: Address, asset: String, lock_secs: u64, duration_secs: u64) -> u64 {
-        let now = env.ledger().timestamp();
-        if lock_secs == 0 || duration_secs == 0 { Env::panic_with_error(&env, Err::InvalidTimes); }
-        let lock_ts = now.saturating_add(lock_secs);
-        let settle_ts = lock_ts.saturating_add(duration_secs);
-
-        let id = get_next_id(&env);
-        put_next_id(&env, id.saturating_add(1));
-
-        let r = Round {
-            creator,
-            asset,
-            lock_ts,
-            settle_ts,
-            status: RoundStatus::Created,
-            lock_price: None,
-            lock_decimals: 0,
-            settle_price: None,
-            settle_decimals: 0,
-            up_count: 0,
-            down_count: 0,
-        };
-        put_round(&env, id, &r);
-        env.events().publish((symbol_short!("create"), id), (r.lock_ts, r.settle_ts));
-        id
+    /// Stakes are taken in the currently configured token; `mode` picks how both prices are read.
+    pub fn create_round(env: Env, creator: Address, asset: String, lock_secs: u64, duration_secs: u64, mode: SettleMode) -> u64 {
+        new_round(&env, creator, asset, lock_secs, duration_secs, mode, RoundKind::UpDown)
     }
 
-    /// side: 0 = Down, 1 = Up
-    pub fn join(env: Env, id: u64, who: Address, side: u32) {
+    /// Like `create_round`, but players pick one of the price buckets between consecutive
+    /// `edges` (ascending, at 14 decimals) and the bucket containing the settle price wins.
+    /// `[60_000e14, 62_000e14, 64_000e14]` offers [60k, 62k) and [62k, 64k).
+    pub fn create_range_round(env: Env, creator: Address, asset: String, lock_secs: u64, duration_secs: u64, mode: SettleMode, edges: Vec<i128>) -> u64 {
+        if edges.len() < 3 || edges.len() > MAX_BUCKETS + 1 { Env::panic_with_error(&env, Err::BadBuckets); }
+        for i in 1..edges.len() {
+            if edges.get_unchecked(i) <= edges.get_unchecked(i - 1) { Env::panic_with_error(&env, Err::BadBuckets); }
+        }
+        new_round(&env, creator, asset, lock_secs, duration_secs, mode, RoundKind::Range(edges))
+    }
+
+    /// side: 0 = Down, 1 = Up for Up/Down rounds, the bucket index for range rounds.
+    /// `amount` of the round token is escrowed in this contract.
+    pub fn join(env: Env, id: u64, who: Address, side: u32, amount: i128) {
+        who.require_auth();
         let mut r = get_round(&env, id);
+        if r.status != RoundStatus::Created { Env::panic_with_error(&env, Err::JoinClosed); }
         let now = env.ledger().timestamp();
         if now >= r.lock_ts { Env::panic_with_error(&env, Err::JoinClosed); }
-        if env.storage().instance().has(&DataKey::Joined(id, who.clone())) {
+        if amount <= 0 { Env::panic_with_error(&env, Err::BadAmount); }
+        if env.storage().persistent().has(&DataKey::Position(id, who.clone())) {
             Env::panic_with_error(&env, Err::AlreadyJoined);
         }
+        if side >= r.pools.len() { Env::panic_with_error(&env, Err::BadSide); }
+        token::Client::new(&env, &r.token).transfer(&who, &env.current_contract_address(), &amount);
+
         // record join
-        env.storage().instance().set(&DataKey::Joined(id, who.clone()), &true);
-        if side == 1 { r.up_count = r.up_count.saturating_add(1); } else { r.down_count = r.down_count.saturating_add(1); }
+        put_position(&env, id, &who, &Position { side, amount, claimed: false, payout: 0 });
+        index_push(&env, RoundIndex::Player(who.clone()), id);
+        r.counts.set(side, r.counts.get_unchecked(side).saturating_add(1));
+        let pool = r.pools.get_unchecked(side).checked_add(amount).unwrap_or_else(|| Env::panic_with_error(&env, Err::MathOverflow));
+        r.pools.set(side, pool);
         put_round(&env, id, &r);
-        env.events().publish((symbol_short!("join"), id), (who, side));
+        env.events().publish((symbol_short!("join"), id), (who, side, amount));
     }
 
-    /// Snapshot the lock price via oracle; callable at/after lock_ts.
+    /// Snapshot the lock price via oracle (per the round's mode); callable at/after lock_ts.
     pub fn lock(env: Env, id: u64) {
         let mut r = get_round(&env, id);
         if r.status != RoundStatus::Created { Env::panic_with_error(&env, Err::BadState); }
         let now = env.ledger().timestamp();
         if now < r.lock_ts { Env::panic_with_error(&env, Err::BadState); }
-        let oracle = read_oracle(&env);
-        // (price, decimals, ts)
-        let (p,d,_): (i128,u32,u64) = env.invoke_contract(&oracle, &symbol_short!("get_spot"), vec![&env, r.asset.clone().into_val(&env), Option::<u64>::None.into_val(&env)]);
+        let (p, d) = read_price(&env, &r, r.lock_ts);
         r.lock_price = Some(p);
         r.lock_decimals = d;
         r.status = RoundStatus::Locked;
@@ -174,20 +673,77 @@ impl RoundEngine {
         if r.status != RoundStatus::Locked { Env::panic_with_error(&env, Err::BadState); }
         let now = env.ledger().timestamp();
         if now < r.settle_ts { Env::panic_with_error(&env, Err::BadState); }
-        let oracle = read_oracle(&env);
-        let (p, d, _ts): (i128, u32, u64) = env.invoke_contract(&oracle, &symbol_short!("get_spot"), vec![&env, r.pair.clone().into_val(&env), Option::<u64>::None.into_val(&env)]);
+        let (p, d) = read_price(&env, &r, r.settle_ts);
         r.settle_price = Some(p);
         r.settle_decimals = d;
         r.status = RoundStatus::Settled;
-        put_round(&env, id, &r);
+        deactivate(&env, &r, id);
 
-        let mut winner: u32 = 2; // 0=down,1=up,2=tie
-        if let (Some(lp), Some(sp)) = (r.lock_price, r.settle_price) {
-            if sp > lp { winner = 1; } else if sp < lp { winner = 0; } else { winner = 2; }
+        let winner = match r.lock_price {
+            Some(lp) => winning_side(&r, lp, p),
+            None => r.pools.len(),
+        };
+        r.winner = Some(winner);
+        if !is_refundable(&r) {
+            r.fee_bps = read_fee_cfg(&env).map(|c| c.fee_bps).unwrap_or(0);
         }
+        put_round(&env, id, &r);
         env.events().publish((symbol_short!("settle"), id), (p, d, winner));
     }
 
+    /// Pay a winner of a settled round their stake plus a pro-rata share of the losing pool.
+    /// The protocol fee on the winnings is reduced by the claimant's KALE Pass discount.
+    pub fn claim(env: Env, id: u64, who: Address) -> i128 {
+        who.require_auth();
+        let mut r = get_round(&env, id);
+        if r.status != RoundStatus::Settled || is_refundable(&r) { Env::panic_with_error(&env, Err::BadState); }
+        let mut pos = get_position(&env, id, &who);
+        let (win_pool, lose_pool) = match r.winner {
+            Some(w) if w < r.pools.len() => win_lose_pools(&r, w),
+            _ => Env::panic_with_error(&env, Err::NotWinner),
+        };
+        if Some(pos.side) != r.winner { Env::panic_with_error(&env, Err::NotWinner); }
+        if pos.claimed { Env::panic_with_error(&env, Err::AlreadyClaimed); }
+
+        let share = winner_share(&env, pos.amount, win_pool, lose_pool);
+        let full_fee = bps_of(&env, share, r.fee_bps);
+        let discount_bps = if full_fee > 0 { read_discount_bps(&env, &who) } else { 0 };
+        let saved = bps_of(&env, full_fee, discount_bps);
+        let fee = full_fee - saved;
+        let payout = pos.amount + share - fee;
+        pos.claimed = true;
+        pos.payout = payout;
+        put_position(&env, id, &who, &pos);
+
+        token::Client::new(&env, &r.token).transfer(&env.current_contract_address(), &who, &payout);
+        if fee > 0 {
+            route_fee(&env, id, &r, fee);
+            r.fee = r.fee.saturating_add(fee);
+        }
+        r.paid += 1;
+        put_round(&env, id, &r);
+        env.events().publish((symbol_short!("claim"), id), (who, payout, fee, discount_bps, saved));
+        payout
+    }
+
+    /// Return a participant's escrowed stake for a canceled, tied or one-sided round.
+    /// Anyone may trigger it; the stake always goes back to `who`.
+    pub fn refund(env: Env, id: u64, who: Address) -> i128 {
+        let mut r = get_round(&env, id);
+        if !is_refundable(&r) { Env::panic_with_error(&env, Err::NotRefundable); }
+        let mut pos = get_position(&env, id, &who);
+        if pos.claimed { Env::panic_with_error(&env, Err::AlreadyRefunded); }
+        pos.claimed = true;
+        pos.payout = pos.amount;
+        put_position(&env, id, &who, &pos);
+        r.paid += 1;
+        put_round(&env, id, &r);
+
+        token::Client::new(&env, &r.token).transfer(&env.current_contract_address(), &who, &pos.amount);
+        env.events().publish((symbol_short!("refund"), id), (who, pos.amount));
+        pos.amount
+    }
+
     pub fn cancel(env: Env, id: u64) {
         require_admin(&env);
         let mut r = get_round(&env, id);
@@ -195,11 +751,78 @@ impl RoundEngine {
             Env::panic_with_error(&env, Err::BadState);
         }
         r.status = RoundStatus::Canceled;
+        deactivate(&env, &r, id);
         put_round(&env, id, &r);
         env.events().publish((symbol_short!("cancel"), id), ());
     }
 
+    /// Send every `token` fee held back after the hub rejected it to `to` (admin only).
+    pub fn sweep_fees(env: Env, token: Address, to: Address) -> i128 {
+        require_admin(&env);
+        let held = read_held_fees(&env, &token);
+        if held == 0 { return 0; }
+        env.storage().instance().remove(&DataKey::HeldFees(token.clone()));
+        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &to, &held);
+        env.events().publish((symbol_short!("sweep"),), (token, to, held));
+        held
+    }
+
+    /// `token` fees held back after the hub rejected them, awaiting `sweep_fees`.
+    pub fn held_fees(env: Env, token: Address) -> i128 { read_held_fees(&env, &token) }
+
     pub fn get_round(env: Env, id: u64) -> Round { get_round(&env, id) }
+
+    /// Rounds in creation order, optionally only those in `status` and/or on `asset`.
+    /// Start with `cursor = 0` and follow `next_cursor`; pages hold at most 50 rounds.
+    pub fn list_rounds(env: Env, status: Option<RoundStatus>, asset: Option<String>, cursor: u64, limit: u32) -> RoundPage {
+        match asset {
+            Some(asset) => {
+                let index = RoundIndex::Asset(asset);
+                page(&env, index_len(&env, &index), |pos| index_at(&env, &index, pos), status, cursor, limit)
+            }
+            // ids are handed out sequentially, so they are their own global index
+            None => page(&env, get_next_id(&env), |id| id, status, cursor, limit),
+        }
+    }
+
+    /// Rounds created by `creator`, in creation order.
+    pub fn rounds_by_creator(env: Env, creator: Address, cursor: u64, limit: u32) -> RoundPage {
+        let index = RoundIndex::Creator(creator);
+        page(&env, index_len(&env, &index), |pos| index_at(&env, &index, pos), None, cursor, limit)
+    }
+
+    /// `user`'s position in round `id`, if they joined it and it hasn't expired.
+    pub fn position(env: Env, id: u64, user: Address) -> Option<Position> {
+        find_position(&env, id, &user)
+    }
+
+    /// Every position `user` has taken, open and historical, in the order they joined.
+    /// Positions that expired after payout (or whose round expired) are skipped; the index
+    /// entries pointing at them are bumped as they are read, so the walk never hits archived ones.
+    pub fn positions_of(env: Env, user: Address, cursor: u64, limit: u32) -> PositionPage {
+        env.storage().instance().extend_ttl(ROUND_TTL_THRESHOLD, ROUND_TTL);
+        let index = RoundIndex::Player(user.clone());
+        let len = index_len(&env, &index);
+        let limit = limit.min(MAX_PAGE);
+        let mut positions = Vec::new(&env);
+        let mut at = cursor;
+        let mut scanned = 0;
+        while at < len && positions.len() < limit && scanned < MAX_SCAN {
+            let id = index_at(&env, &index, at);
+            if let (Some(round), Some(position)) = (find_round(&env, id), find_position(&env, id, &user)) {
+                positions.push_back(PositionEntry { id, status: round.status, position });
+            }
+            at += 1;
+            scanned += 1;
+        }
+        PositionPage { positions, next_cursor: if at < len { Some(at) } else { None } }
+    }
+
+    /// Rounds on `asset` still open for joining or awaiting settlement, paged like `list_rounds`.
+    /// Order is not stable: when a round leaves the set the last one takes its place.
+    pub fn active_rounds(env: Env, asset: String, cursor: u64, limit: u32) -> RoundPage {
+        page(&env, active_len(&env, &asset), |slot| active_at(&env, &asset, slot), None, cursor, limit)
+    }
 }
 
 // Bring IntoVal for invoke_contract args
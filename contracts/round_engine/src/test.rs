@@ -2,19 +2,82 @@
 extern crate std;
 
 use super::*;
-use soroban_sdk::{ testutils::{Address as TestAddress, Ledger}, contract, contractimpl, Address, Env, String };
+use oracle_interface::OracleInterface;
+use soroban_sdk::{
+    testutils::{storage::{Persistent as _, Temporary as _}, Address as TestAddress, Ledger, MockAuth, MockAuthInvoke},
+    contract, contractimpl, symbol_short, token, Address, Env, IntoVal, String,
+};
 
 
-// ---- Mock Oracle used by tests ----
+// ---- Mock Oracle used by tests (same interface as oracle_adapter) ----
 #[contract]
 struct MockOracle;
 #[contractimpl]
-impl MockOracle {
-    pub fn get_spot(env: Env, _pair: String, _override_max: Option<u64>) -> (i128, u32, u64) {
+impl OracleInterface for MockOracle {
+    fn get_spot(env: Env, asset: String, out_decimals: u32) -> (i128, u32, u64) {
         let now = env.ledger().timestamp();
+        Self::get_price_at(env, asset, now, out_decimals)
+    }
+    // averages are offset from spot so tests can tell which call a round used
+    fn get_twap(env: Env, asset: String, records: u32, out_decimals: u32) -> (i128, u32, u64) {
+        let (p, d, ts) = Self::get_spot(env, asset, out_decimals);
+        (p + records as i128, d, ts)
+    }
+    fn get_twap_window(env: Env, asset: String, window_secs: u64, out_decimals: u32) -> (i128, u32, u64) {
+        let (p, d, ts) = Self::get_spot(env, asset, out_decimals);
+        (p - window_secs as i128, d, ts)
+    }
+    fn get_price_at(_env: Env, _asset: String, ts: u64, out_decimals: u32) -> (i128, u32, u64) {
         // deterministic, increasing-ish value
-        let price = 1_000_000_00i128 + ((now % 10) as i128) * 1_000_000i128;
-        (price, 8u32, now)
+        let price = 100_000_000i128 + ((ts % 10) as i128) * 1_000_000i128;
+        (price, out_decimals, ts)
+    }
+    fn get_twap_at(env: Env, asset: String, ts: u64, records: u32, out_decimals: u32) -> (i128, u32, u64) {
+        let (p, d, ts) = Self::get_price_at(env, asset, ts, out_decimals);
+        (p + records as i128, d, ts)
+    }
+    fn get_twap_window_at(env: Env, asset: String, ts: u64, window_secs: u64, out_decimals: u32) -> (i128, u32, u64) {
+        let (p, d, ts) = Self::get_price_at(env, asset, ts, out_decimals);
+        (p - window_secs as i128, d, ts)
+    }
+}
+
+// ---- Mock creator_hub: pulls the fee and records the last credit ----
+#[contract]
+struct MockHub;
+#[contractimpl]
+impl MockHub {
+    pub fn credit_fees(env: Env, caller: Address, token: Address, platform: Address, creator: Address, staker_pool: Address, total_fee: i128) {
+        caller.require_auth();
+        token::Client::new(&env, &token).transfer(&caller, &env.current_contract_address(), &total_fee);
+        if env.storage().instance().has(&symbol_short!("reject")) {
+            // the engine isn't registered as a fee source
+            Env::panic_with_error(&env, soroban_sdk::Error::from_contract_error(4));
+        }
+        env.storage().instance().set(&symbol_short!("last"), &(platform, creator, staker_pool, total_fee));
+    }
+    pub fn reject(env: Env) {
+        env.storage().instance().set(&symbol_short!("reject"), &true);
+    }
+    pub fn last(env: Env) -> Option<(Address, Address, Address, i128)> {
+        env.storage().instance().get(&symbol_short!("last"))
+    }
+}
+
+// ---- Mock kale_pass_treasury: per-user discount set by the test ----
+#[contract]
+struct MockTreasury;
+#[contractimpl]
+impl MockTreasury {
+    pub fn set(env: Env, user: Address, bps: u32) {
+        env.storage().instance().set(&user, &bps);
+    }
+    pub fn get_discount_bps(env: Env, user: Address) -> u32 {
+        if env.storage().instance().has(&symbol_short!("trap")) { panic!("treasury down"); }
+        env.storage().instance().get(&user).unwrap_or(0)
+    }
+    pub fn trap(env: Env) {
+        env.storage().instance().set(&symbol_short!("trap"), &true);
     }
 }
 
@@ -26,15 +89,17 @@ fn setup_auth() -> (Env, RoundEngineClient<'static>, Address, Address, Address)
         l.sequence_number = 1;
     });
     env.mock_all_auths(); // positive-path auth
-    let re_id = env.register_contract(None, RoundEngine);
+    let re_id = env.register(RoundEngine, ());
     let client = RoundEngineClient::new(&env, &re_id);
 
-    let oracle_id = env.register_contract(None, MockOracle);
+    let oracle_id = env.register(MockOracle, ());
     let admin = <Address as TestAddress>::generate(&env);
     let creator = <Address as TestAddress>::generate(&env);
+    let token = env.register_stellar_asset_contract_v2(admin.clone()).address();
 
     client.init(&admin);
     client.set_oracle(&oracle_id);
+    client.set_token(&token);
     (env, client, admin, creator, oracle_id)
 }
 
@@ -44,16 +109,57 @@ fn setup_plain() -> (Env, RoundEngineClient<'static>, Address, Address) {
         l.timestamp = 1_700_000_000;
         l.sequence_number = 1;
     });
-    let re_id = env.register_contract(None, RoundEngine);
+    let re_id = env.register(RoundEngine, ());
     let client = RoundEngineClient::new(&env, &re_id);
 
     let admin = <Address as TestAddress>::generate(&env);
     let creator = <Address as TestAddress>::generate(&env);
+    let token = env.register_stellar_asset_contract_v2(admin.clone()).address();
 
     client.init(&admin);
+    // authorize only the token setup so later admin calls still fail
+    client
+        .mock_auths(&[MockAuth {
+            address: &admin,
+            invoke: &MockAuthInvoke {
+                contract: &re_id,
+                fn_name: "set_token",
+                args: (&token,).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .set_token(&token);
     (env, client, admin, creator)
 }
 
+/// Create a round with only the creator's signature, for tests without mock_all_auths.
+fn signed_round(env: &Env, client: &RoundEngineClient, creator: &Address) -> u64 {
+    let pair = String::from_str(env, "btc/usdc");
+    client
+        .mock_auths(&[MockAuth {
+            address: creator,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "create_round",
+                args: (creator, pair.clone(), 10u64, 10u64, SettleMode::Spot).into_val(env),
+                sub_invokes: &[],
+            },
+        }])
+        .create_round(creator, &pair, &10u64, &10u64, &SettleMode::Spot)
+}
+
+/// Fresh address funded with `amount` of the round's stake token.
+fn player(env: &Env, client: &RoundEngineClient, id: u64, amount: i128) -> Address {
+    let who = <Address as TestAddress>::generate(env);
+    let token = client.get_round(&id).token;
+    token::StellarAssetClient::new(env, &token).mint(&who, &amount);
+    who
+}
+
+fn balance(env: &Env, client: &RoundEngineClient, id: u64, who: &Address) -> i128 {
+    token::Client::new(env, &client.get_round(&id).token).balance(who)
+}
+
 // -------- Tests --------
 
 #[test]
@@ -61,7 +167,7 @@ fn init_and_set_oracle_ok() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     // create one round quickly just to assert a happy path
     let asset = String::from_str(&env, "BTC");
-    let id = client.create_round(&creator, &asset, &5u64, &5u64);
+    let id = client.create_round(&creator, &asset, &5u64, &5u64, &SettleMode::Spot);
     // NOTE: the signature in lib is (creator, pair, lock_secs, duration_secs); adjust if needed:
     // let id = client.create_round(&creator, &pair, &5u64, &5u64);
     // (If you changed the signature earlier, keep that version.)
@@ -73,7 +179,7 @@ fn init_and_set_oracle_ok() {
 #[should_panic]
 fn set_oracle_requires_admin_auth() {
     let (env, client, _admin, _creator) = setup_plain();
-    let fake_oracle = env.register_contract(None, MockOracle);
+    let fake_oracle = env.register(MockOracle, ());
     // without mock_all_auths, require_admin should fail
     client.set_oracle(&fake_oracle);
 }
@@ -84,21 +190,26 @@ fn create_round_invalid_times_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "eth/usdc");
     // lock_secs = 0 -> Err::InvalidTimes
-    client.create_round(&creator, &pair, &0u64, &10u64);
+    client.create_round(&creator, &pair, &0u64, &10u64, &SettleMode::Spot);
 }
 
 #[test]
 fn join_ok_and_counts() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "xrp/usdc");
-    let id = client.create_round(&creator, &pair, &10u64, &10u64);
-    let p1 = <Address as TestAddress>::generate(&env);
-    let p2 = <Address as TestAddress>::generate(&env);
-    client.join(&id, &p1, &1u32);
-    client.join(&id, &p2, &0u32);
+    let id = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    let p1 = player(&env, &client, id, 100);
+    let p2 = player(&env, &client, id, 100);
+    client.join(&id, &p1, &1u32, &60i128);
+    client.join(&id, &p2, &0u32, &25i128);
     let r = client.get_round(&id);
-    assert_eq!(r.up_count, 1);
-    assert_eq!(r.down_count, 1);
+    assert_eq!(r.counts.get_unchecked(1), 1);
+    assert_eq!(r.counts.get_unchecked(0), 1);
+    assert_eq!(r.pools.get_unchecked(1), 60);
+    assert_eq!(r.pools.get_unchecked(0), 25);
+    // stakes are escrowed in the engine
+    assert_eq!(balance(&env, &client, id, &p1), 40);
+    assert_eq!(balance(&env, &client, id, &client.address), 85);
 }
 
 #[test]
@@ -106,13 +217,13 @@ fn join_ok_and_counts() {
 fn join_after_lock_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "sol/usdc");
-    let id = client.create_round(&creator, &pair, &1u64, &10u64);
+    let id = client.create_round(&creator, &pair, &1u64, &10u64, &SettleMode::Spot);
     env.ledger().with_mut(|l| {
         l.timestamp += 1; // now == lock_ts
         l.sequence_number += 1;
     });
-    let who = <Address as TestAddress>::generate(&env);
-    client.join(&id, &who, &1u32);
+    let who = player(&env, &client, id, 100);
+    client.join(&id, &who, &1u32, &10i128);
 }
 
 #[test]
@@ -120,10 +231,10 @@ fn join_after_lock_panics() {
 fn join_double_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "ada/usdc");
-    let id = client.create_round(&creator, &pair, &10u64, &10u64);
-    let who = <Address as TestAddress>::generate(&env);
-    client.join(&id, &who, &1u32);
-    client.join(&id, &who, &0u32); // second time -> AlreadyJoined
+    let id = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    let who = player(&env, &client, id, 100);
+    client.join(&id, &who, &1u32, &10i128);
+    client.join(&id, &who, &0u32, &10i128); // second time -> AlreadyJoined
 }
 
 #[test]
@@ -131,7 +242,7 @@ fn join_double_panics() {
 fn lock_before_time_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "dot/usdc");
-    let id = client.create_round(&creator, &pair, &5u64, &5u64);
+    let id = client.create_round(&creator, &pair, &5u64, &5u64, &SettleMode::Spot);
     // now < lock_ts
     client.lock(&id);
 }
@@ -141,7 +252,7 @@ fn lock_before_time_panics() {
 fn lock_wrong_state_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "arb/usdc");
-    let id = client.create_round(&creator, &pair, &1u64, &5u64);
+    let id = client.create_round(&creator, &pair, &1u64, &5u64, &SettleMode::Spot);
     env.ledger().with_mut(|l| { l.timestamp += 1; l.sequence_number += 1; });
     client.lock(&id);
     // second lock should fail (status != Created)
@@ -153,7 +264,7 @@ fn lock_wrong_state_panics() {
 fn settle_before_time_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "op/usdc");
-    let id = client.create_round(&creator, &pair, &1u64, &5u64);
+    let id = client.create_round(&creator, &pair, &1u64, &5u64, &SettleMode::Spot);
     env.ledger().with_mut(|l| { l.timestamp += 1; l.sequence_number += 1; });
     client.lock(&id);
     // now < settle_ts
@@ -165,7 +276,7 @@ fn settle_before_time_panics() {
 fn settle_wrong_state_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "sei/usdc");
-    let id = client.create_round(&creator, &pair, &1u64, &1u64);
+    let id = client.create_round(&creator, &pair, &1u64, &1u64, &SettleMode::Spot);
     // settle without lock -> BadState
     client.settle(&id);
 }
@@ -174,13 +285,13 @@ fn settle_wrong_state_panics() {
 fn settle_ok_updates_state() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "btc/usdc");
-    let id = client.create_round(&creator, &pair, &2u64, &3u64);
+    let id = client.create_round(&creator, &pair, &2u64, &3u64, &SettleMode::Spot);
 
     // join a couple so flow looks realistic
-    let a = <Address as TestAddress>::generate(&env);
-    let b = <Address as TestAddress>::generate(&env);
-    client.join(&id, &a, &1u32);
-    client.join(&id, &b, &0u32);
+    let a = player(&env, &client, id, 100);
+    let b = player(&env, &client, id, 100);
+    client.join(&id, &a, &1u32, &10i128);
+    client.join(&id, &b, &0u32, &10i128);
 
     env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
     client.lock(&id);
@@ -192,14 +303,14 @@ fn settle_ok_updates_state() {
     assert!(matches!(r.status, RoundStatus::Settled));
     assert!(r.lock_price.is_some());
     assert!(r.settle_price.is_some());
+    assert_eq!(r.winner, Some(1)); // mock price rises between lock and settle
 }
 
 #[test]
 #[should_panic]
 fn cancel_requires_admin_auth() {
     let (env, client, _admin, creator) = setup_plain();
-    let pair = String::from_str(&env, "link/usdc");
-    let id = client.create_round(&creator, &pair, &10u64, &10u64);
+    let id = signed_round(&env, &client, &creator);
     // no mock_all_auths => require_admin should fail
     client.cancel(&id);
 }
@@ -208,7 +319,7 @@ fn cancel_requires_admin_auth() {
 fn cancel_ok_before_settle() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "inj/usdc");
-    let id = client.create_round(&creator, &pair, &10u64, &10u64);
+    let id = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
     client.cancel(&id);
     let r = client.get_round(&id);
     assert!(matches!(r.status, RoundStatus::Canceled));
@@ -219,7 +330,7 @@ fn cancel_ok_before_settle() {
 fn cancel_after_settle_panics() {
     let (env, client, _admin, creator, _oracle) = setup_auth();
     let pair = String::from_str(&env, "atom/usdc");
-    let id = client.create_round(&creator, &pair, &1u64, &1u64);
+    let id = client.create_round(&creator, &pair, &1u64, &1u64, &SettleMode::Spot);
     env.ledger().with_mut(|l| { l.timestamp += 1; l.sequence_number += 1; });
     client.lock(&id);
     env.ledger().with_mut(|l| { l.timestamp += 1; l.sequence_number += 1; });
@@ -233,3 +344,651 @@ fn get_round_unknown_panics() {
     let (_env, client, _admin, _creator, _oracle) = setup_auth();
     let _ = client.get_round(&999_999u64); // not created -> RoundNotFound
 }
+
+#[test]
+#[should_panic]
+fn join_zero_amount_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "near/usdc");
+    let id = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    let who = player(&env, &client, id, 100);
+    client.join(&id, &who, &1u32, &0i128); // -> BadAmount
+}
+
+#[test]
+#[should_panic]
+fn join_requires_player_auth() {
+    let (env, client, _admin, creator) = setup_plain();
+    let id = signed_round(&env, &client, &creator);
+    let who = <Address as TestAddress>::generate(&env);
+    client.join(&id, &who, &1u32, &10i128); // no auth -> panic
+}
+
+// Lock at +2s and settle at +5s: the mock oracle price rises, so Up wins.
+fn settled_round(env: &Env, client: &RoundEngineClient, creator: &Address, stakes: &[(u32, i128)]) -> (u64, std::vec::Vec<Address>) {
+    let pair = String::from_str(env, "btc/usdc");
+    let id = client.create_round(creator, &pair, &2u64, &3u64, &SettleMode::Spot);
+    let mut players = std::vec::Vec::new();
+    for (side, amount) in stakes.iter() {
+        let who = player(env, client, id, 1_000);
+        client.join(&id, &who, side, amount);
+        players.push(who);
+    }
+    env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
+    client.lock(&id);
+    env.ledger().with_mut(|l| { l.timestamp += 3; l.sequence_number += 1; });
+    client.settle(&id);
+    (id, players)
+}
+
+#[test]
+fn claim_pays_winners_pro_rata() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    // Up: 100 + 300, Down: 200 + 100
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 300), (0, 200), (0, 100)]);
+
+    // 100 + 100 * 300 / 400 = 175 ; 300 + 300 * 300 / 400 = 525
+    assert_eq!(client.claim(&id, &p[0]), 175);
+    assert_eq!(client.claim(&id, &p[1]), 525);
+    assert_eq!(balance(&env, &client, id, &p[0]), 1_075);
+    assert_eq!(balance(&env, &client, id, &p[1]), 1_225);
+    assert_eq!(balance(&env, &client, id, &client.address), 0);
+}
+
+#[test]
+fn claim_rounding_dust_stays_in_contract() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 1), (1, 2), (0, 10)]);
+    // 1 + 10/3 = 4 ; 2 + 20/3 = 8 ; one unit of dust left
+    assert_eq!(client.claim(&id, &p[0]), 4);
+    assert_eq!(client.claim(&id, &p[1]), 8);
+    assert_eq!(balance(&env, &client, id, &client.address), 1);
+}
+
+#[test]
+#[should_panic]
+fn claim_by_loser_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 100)]);
+    client.claim(&id, &p[1]); // -> NotWinner
+}
+
+#[test]
+#[should_panic]
+fn claim_twice_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 100)]);
+    client.claim(&id, &p[0]);
+    client.claim(&id, &p[0]); // -> AlreadyClaimed
+}
+
+#[test]
+fn rounds_and_positions_live_in_persistent_storage() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 100)]);
+    env.as_contract(&client.address, || {
+        assert!(!env.storage().instance().has(&DataKey::Round(id)));
+        assert!(env.storage().persistent().get_ttl(&DataKey::Round(id)) >= ROUND_TTL_THRESHOLD);
+        assert!(env.storage().persistent().get_ttl(&DataKey::Position(id, p[0].clone())) >= ROUND_TTL_THRESHOLD);
+    });
+}
+
+#[test]
+fn paid_out_round_moves_to_temporary_storage_and_expires() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 100)]);
+    // the loser is owed nothing, so the winner's claim closes the round out
+    client.claim(&id, &p[0]);
+    env.as_contract(&client.address, || {
+        assert!(!env.storage().persistent().has(&DataKey::Round(id)));
+        assert!(env.storage().temporary().get_ttl(&DataKey::Round(id)) >= GRACE_TTL);
+    });
+    assert_eq!(client.get_round(&id).paid, 1);
+    assert_eq!(client.list_rounds(&None, &None, &0, &10).rounds.len(), 1);
+
+    env.ledger().with_mut(|l| l.sequence_number += GRACE_TTL + 1);
+    assert_eq!(
+        client.try_get_round(&id).err(),
+        Some(Ok(soroban_sdk::Error::from_contract_error(Err::RoundNotFound as u32)))
+    );
+    assert_eq!(client.list_rounds(&None, &None, &0, &10).rounds.len(), 0);
+}
+
+#[test]
+fn refunded_round_expires_once_every_stake_is_returned() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 50)]); // one-sided
+    client.refund(&id, &p[0]);
+    env.as_contract(&client.address, || assert!(env.storage().persistent().has(&DataKey::Round(id))));
+    client.refund(&id, &p[1]);
+    env.as_contract(&client.address, || assert!(env.storage().temporary().has(&DataKey::Round(id))));
+}
+
+#[test]
+fn claim_after_marker_expiry_still_rejected() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 50), (0, 100)]);
+    client.claim(&id, &p[0]);
+    // the paid-out position leaves persistent storage; the round stays until p[1] claims
+    env.as_contract(&client.address, || {
+        assert!(!env.storage().persistent().has(&DataKey::Position(id, p[0].clone())));
+        assert!(env.storage().temporary().get_ttl(&DataKey::Position(id, p[0].clone())) >= GRACE_TTL);
+    });
+    assert_eq!(
+        client.try_claim(&id, &p[0]).err(),
+        Some(Ok(soroban_sdk::Error::from_contract_error(Err::AlreadyClaimed as u32)))
+    );
+
+    env.ledger().with_mut(|l| l.sequence_number += GRACE_TTL + 1);
+    let not_participant = soroban_sdk::Error::from_contract_error(Err::NotParticipant as u32);
+    assert_eq!(client.try_claim(&id, &p[0]).err(), Some(Ok(not_participant)));
+    assert_eq!(client.position(&id, &p[0]), None);
+    // p[1] hasn't claimed, so their position and the round are still there
+    assert!(client.position(&id, &p[1]).is_some());
+    assert_eq!(client.get_round(&id).creator, creator);
+}
+
+#[test]
+fn positions_of_skips_expired_entries() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (done, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 50)]);
+    let winner = &p[0];
+    client.claim(&done, winner);
+    let pair = String::from_str(&env, "btc/usdc");
+    let open = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    client.join(&open, winner, &1u32, &20i128);
+    assert_eq!(client.positions_of(winner, &0, &10).positions.len(), 2);
+
+    env.ledger().with_mut(|l| l.sequence_number += GRACE_TTL + 1);
+    let page = client.positions_of(winner, &0, &10);
+    assert_eq!(page.positions.len(), 1);
+    assert_eq!(page.positions.get_unchecked(0).id, open);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn positions_of_reads_history_older_than_round_ttl() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (done, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 50)]);
+    let loser = &p[1];
+    let pair = String::from_str(&env, "btc/usdc");
+    let open = client.create_round(&creator, &pair, &(90 * 86_400), &60u64, &SettleMode::Spot);
+    client.join(&open, loser, &1u32, &20i128);
+
+    for _ in 0..3 {
+        env.ledger().with_mut(|l| l.sequence_number += ROUND_TTL - DAY_IN_LEDGERS);
+        let page = client.positions_of(loser, &0, &10);
+        assert_eq!(page.positions.len(), 2);
+        assert_eq!(page.positions.get_unchecked(0).id, done);
+        assert_eq!(page.positions.get_unchecked(1).id, open);
+    }
+}
+
+#[test]
+fn positions_record_outcome_and_history() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 50)]);
+    let (winner, loser) = (&p[0], &p[1]);
+    assert_eq!(client.position(&id, winner), Some(Position { side: 1, amount: 100, claimed: false, payout: 0 }));
+
+    client.claim(&id, winner);
+    assert_eq!(client.position(&id, winner), Some(Position { side: 1, amount: 100, claimed: true, payout: 150 }));
+    assert_eq!(client.position(&id, loser), Some(Position { side: 0, amount: 50, claimed: false, payout: 0 }));
+    assert_eq!(client.position(&id, &creator), None);
+
+    // a later open round shows up after the settled one
+    let pair = String::from_str(&env, "btc/usdc");
+    let open = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    client.join(&open, winner, &0u32, &20i128);
+    let page = client.positions_of(winner, &0, &10);
+    assert_eq!(page.next_cursor, None);
+    assert_eq!(page.positions.len(), 2);
+    let settled = page.positions.get_unchecked(0);
+    assert!(settled.id == id && settled.status == RoundStatus::Settled && settled.position.claimed);
+    let pending = page.positions.get_unchecked(1);
+    assert!(pending.id == open && pending.status == RoundStatus::Created);
+    assert_eq!(pending.position.amount, 20);
+
+    let first = client.positions_of(winner, &0, &1);
+    assert_eq!((first.positions.len(), first.next_cursor), (1, Some(1)));
+}
+
+#[test]
+#[should_panic]
+fn claim_before_settle_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    let id = client.create_round(&creator, &pair, &2u64, &3u64, &SettleMode::Spot);
+    let who = player(&env, &client, id, 100);
+    client.join(&id, &who, &1u32, &10i128);
+    client.claim(&id, &who); // -> BadState
+}
+
+#[test]
+#[should_panic]
+fn claim_non_participant_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, _p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 100)]);
+    let outsider = <Address as TestAddress>::generate(&env);
+    client.claim(&id, &outsider); // -> NotParticipant
+}
+
+#[test]
+fn refund_after_cancel() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    let id = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    let a = player(&env, &client, id, 100);
+    let b = player(&env, &client, id, 100);
+    client.join(&id, &a, &1u32, &40i128);
+    client.join(&id, &b, &0u32, &70i128);
+    client.cancel(&id);
+
+    assert_eq!(client.refund(&id, &a), 40);
+    assert_eq!(client.refund(&id, &b), 70);
+    assert_eq!(client.position(&id, &b), Some(Position { side: 0, amount: 70, claimed: true, payout: 70 }));
+    assert_eq!(balance(&env, &client, id, &a), 100);
+    assert_eq!(balance(&env, &client, id, &b), 100);
+    assert_eq!(balance(&env, &client, id, &client.address), 0);
+}
+
+#[test]
+fn refund_after_tie() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    // lock at +2s, settle at +12s: the mock oracle repeats every 10s -> tie
+    let id = client.create_round(&creator, &pair, &2u64, &10u64, &SettleMode::Spot);
+    let a = player(&env, &client, id, 100);
+    let b = player(&env, &client, id, 100);
+    client.join(&id, &a, &1u32, &30i128);
+    client.join(&id, &b, &0u32, &50i128);
+    env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
+    client.lock(&id);
+    env.ledger().with_mut(|l| { l.timestamp += 10; l.sequence_number += 1; });
+    client.settle(&id);
+    assert_eq!(client.get_round(&id).winner, Some(2));
+
+    assert_eq!(client.refund(&id, &a), 30);
+    assert_eq!(client.refund(&id, &b), 50);
+    assert_eq!(balance(&env, &client, id, &client.address), 0);
+}
+
+#[test]
+fn refund_one_sided_round() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(0, 100), (0, 20)]);
+    assert_eq!(client.refund(&id, &p[0]), 100);
+    assert_eq!(client.refund(&id, &p[1]), 20);
+    assert_eq!(balance(&env, &client, id, &p[0]), 1_000);
+}
+
+#[test]
+#[should_panic]
+fn claim_one_sided_round_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 20)]);
+    client.claim(&id, &p[0]); // -> BadState, refund instead
+}
+
+#[test]
+#[should_panic]
+fn refund_twice_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    let id = client.create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot);
+    let a = player(&env, &client, id, 100);
+    client.join(&id, &a, &1u32, &40i128);
+    client.cancel(&id);
+    client.refund(&id, &a);
+    client.refund(&id, &a); // -> AlreadyRefunded
+}
+
+#[test]
+#[should_panic]
+fn refund_decided_round_panics() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 100)]);
+    client.refund(&id, &p[0]); // -> NotRefundable
+}
+
+#[test]
+fn claims_route_fee_into_hub() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let hub = env.register(MockHub, ());
+    let hub_client = MockHubClient::new(&env, &hub);
+    let platform = <Address as TestAddress>::generate(&env);
+    let stakers = <Address as TestAddress>::generate(&env);
+    client.set_fee_config(&hub, &1_000u32, &platform, &stakers); // 10%
+
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 300), (0, 200), (0, 100)]);
+    assert_eq!(client.get_round(&id).fee_bps, 1_000);
+    assert_eq!(hub_client.last(), None); // nothing charged until winners claim
+
+    // shares of the 300 losing pool: 75 and 225; 10% fee on each: 7 and 22
+    assert_eq!(client.claim(&id, &p[0]), 168);
+    assert_eq!(hub_client.last(), Some((platform.clone(), creator.clone(), stakers.clone(), 7)));
+    assert_eq!(client.claim(&id, &p[1]), 503);
+    assert_eq!(hub_client.last(), Some((platform, creator, stakers, 22)));
+    assert_eq!(client.get_round(&id).fee, 29);
+    assert_eq!(balance(&env, &client, id, &hub), 29);
+    assert_eq!(balance(&env, &client, id, &client.address), 0);
+}
+
+#[test]
+fn kale_pass_discount_reduces_claim_fee() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let hub = env.register(MockHub, ());
+    let treasury = env.register(MockTreasury, ());
+    let platform = <Address as TestAddress>::generate(&env);
+    let stakers = <Address as TestAddress>::generate(&env);
+    client.set_fee_config(&hub, &1_000u32, &platform, &stakers);
+    client.set_treasury(&treasury);
+
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 100), (0, 400)]);
+    MockTreasuryClient::new(&env, &treasury).set(&p[0], &4_000u32); // 40% off
+
+    // share 200 each, full fee 20; p0 saves 8
+    assert_eq!(client.claim(&id, &p[0]), 288);
+    assert_eq!(client.claim(&id, &p[1]), 280);
+    assert_eq!(client.get_round(&id).fee, 32);
+    assert_eq!(balance(&env, &client, id, &hub), 32);
+}
+
+#[test]
+fn rejected_fee_is_held_for_sweep_and_claims_still_pay() {
+    let (env, client, admin, creator, _oracle) = setup_auth();
+    let hub = env.register(MockHub, ());
+    let hub_client = MockHubClient::new(&env, &hub);
+    hub_client.reject();
+    let platform = <Address as TestAddress>::generate(&env);
+    let stakers = <Address as TestAddress>::generate(&env);
+    client.set_fee_config(&hub, &1_000u32, &platform, &stakers);
+
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 300), (0, 200), (0, 100)]);
+    assert_eq!(client.claim(&id, &p[0]), 168);
+    assert_eq!(client.claim(&id, &p[1]), 503);
+    assert_eq!(hub_client.last(), None);
+    assert_eq!(balance(&env, &client, id, &hub), 0);
+
+    // the fee stays in the engine until the admin sweeps it
+    let token = client.get_round(&id).token;
+    assert_eq!(client.get_round(&id).fee, 29);
+    assert_eq!(client.held_fees(&token), 29);
+    assert_eq!(balance(&env, &client, id, &client.address), 29);
+    assert_eq!(client.sweep_fees(&token, &admin), 29);
+    assert_eq!(balance(&env, &client, id, &admin), 29);
+    assert_eq!(client.held_fees(&token), 0);
+    assert_eq!(client.sweep_fees(&token, &admin), 0);
+}
+
+#[test]
+fn failing_treasury_means_no_discount() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let hub = env.register(MockHub, ());
+    let treasury = env.register(MockTreasury, ());
+    let platform = <Address as TestAddress>::generate(&env);
+    let stakers = <Address as TestAddress>::generate(&env);
+    client.set_fee_config(&hub, &1_000u32, &platform, &stakers);
+    client.set_treasury(&treasury);
+
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (0, 400)]);
+    let treasury_client = MockTreasuryClient::new(&env, &treasury);
+    treasury_client.set(&p[0], &4_000u32);
+    treasury_client.trap();
+    // share 400, full 10% fee of 40 charged
+    assert_eq!(client.claim(&id, &p[0]), 460);
+    assert_eq!(balance(&env, &client, id, &hub), 40);
+}
+
+#[test]
+fn no_fee_on_refundable_round() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let hub = env.register(MockHub, ());
+    let platform = <Address as TestAddress>::generate(&env);
+    let stakers = <Address as TestAddress>::generate(&env);
+    client.set_fee_config(&hub, &1_000u32, &platform, &stakers);
+
+    let (id, p) = settled_round(&env, &client, &creator, &[(1, 100), (1, 300)]);
+    assert_eq!(client.get_round(&id).fee_bps, 0);
+    assert_eq!(MockHubClient::new(&env, &hub).last(), None);
+    assert_eq!(client.refund(&id, &p[1]), 300);
+}
+
+#[test]
+#[should_panic]
+fn set_fee_config_rejects_bps_over_100_percent() {
+    let (env, client, _admin, _creator, _oracle) = setup_auth();
+    let hub = env.register(MockHub, ());
+    let platform = <Address as TestAddress>::generate(&env);
+    client.set_fee_config(&hub, &10_001u32, &platform, &platform); // -> BadFee
+}
+
+#[test]
+fn settlement_mode_picks_oracle_price() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    let spot = client.create_round(&creator, &pair, &2u64, &3u64, &SettleMode::Spot);
+    let twap = client.create_round(&creator, &pair, &2u64, &3u64, &SettleMode::Twap(3));
+    let window = client.create_round(&creator, &pair, &2u64, &3u64, &SettleMode::TwapWindow(60));
+    assert_eq!(client.get_round(&twap).mode, SettleMode::Twap(3));
+
+    env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
+    for id in [spot, twap, window] { client.lock(&id); }
+    let base = client.get_round(&spot).lock_price.unwrap();
+    assert_eq!(client.get_round(&spot).lock_decimals, PRICE_DECIMALS);
+    assert_eq!(client.get_round(&twap).lock_price, Some(base + 3));
+    assert_eq!(client.get_round(&window).lock_price, Some(base - 60));
+
+    env.ledger().with_mut(|l| { l.timestamp += 3; l.sequence_number += 1; });
+    for id in [spot, twap, window] { client.settle(&id); }
+    let settled = client.get_round(&twap);
+    assert_eq!(settled.settle_price, Some(client.get_round(&spot).settle_price.unwrap() + 3));
+    assert_eq!(settled.winner, Some(1));
+}
+
+#[test]
+fn late_keeper_still_uses_prices_at_lock_and_settle_times() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    let id = client.create_round(&creator, &pair, &2u64, &3u64, &SettleMode::Spot);
+    let up = player(&env, &client, id, 100);
+    let down = player(&env, &client, id, 100);
+    client.join(&id, &up, &1u32, &10i128);
+    client.join(&id, &down, &0u32, &10i128);
+
+    // the keeper only shows up after the mock price has wrapped below the lock price
+    let settle_ts = client.get_round(&id).settle_ts;
+    env.ledger().with_mut(|l| { l.timestamp = settle_ts + 6; l.sequence_number += 1; });
+    client.lock(&id);
+    client.settle(&id);
+
+    let r = client.get_round(&id);
+    assert_eq!(r.lock_price, Some(102_000_000));
+    assert_eq!(r.settle_price, Some(105_000_000));
+    assert_eq!(r.winner, Some(1));
+    assert_eq!(client.claim(&id, &up), 20);
+}
+
+#[test]
+fn empty_twap_modes_rejected() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    assert!(client.try_create_round(&creator, &pair, &2u64, &3u64, &SettleMode::Twap(0)).is_err());
+    assert!(client.try_create_round(&creator, &pair, &2u64, &3u64, &SettleMode::TwapWindow(0)).is_err());
+}
+
+#[test]
+fn listing_rounds_by_status_asset_and_creator() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let other = <Address as TestAddress>::generate(&env);
+    let btc = String::from_str(&env, "btc/usdc");
+    let eth = String::from_str(&env, "eth/usdc");
+    let b0 = client.create_round(&creator, &btc, &2u64, &3u64, &SettleMode::Spot);
+    let e0 = client.create_round(&other, &eth, &2u64, &3u64, &SettleMode::Spot);
+    let b1 = client.create_round(&other, &btc, &2u64, &3u64, &SettleMode::Spot);
+    let b2 = client.create_round(&creator, &btc, &100u64, &3u64, &SettleMode::Spot);
+    let ids = |p: &RoundPage| p.rounds.iter().map(|e| e.id).collect::<std::vec::Vec<_>>();
+
+    assert_eq!(ids(&client.list_rounds(&None, &None, &0, &10)), [b0, e0, b1, b2]);
+    assert_eq!(ids(&client.list_rounds(&None, &Some(btc.clone()), &0, &10)), [b0, b1, b2]);
+    assert_eq!(ids(&client.rounds_by_creator(&other, &0, &10)), [e0, b1]);
+
+    // b0 settles, b1 is canceled, b2 stays open
+    env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
+    client.lock(&b0);
+    client.lock(&e0);
+    client.cancel(&b1);
+    env.ledger().with_mut(|l| { l.timestamp += 3; l.sequence_number += 1; });
+    client.settle(&b0);
+
+    let settled = client.list_rounds(&Some(RoundStatus::Settled), &None, &0, &10);
+    assert_eq!(ids(&settled), [b0]);
+    assert_eq!(settled.next_cursor, None);
+    assert_eq!(ids(&client.list_rounds(&Some(RoundStatus::Locked), &None, &0, &10)), [e0]);
+    assert_eq!(ids(&client.list_rounds(&Some(RoundStatus::Canceled), &Some(btc.clone()), &0, &10)), [b1]);
+    assert_eq!(ids(&client.active_rounds(&btc, &0, &10)), [b2]);
+    assert!(client.active_rounds(&eth, &0, &10).rounds.get_unchecked(0).round.status == RoundStatus::Locked);
+}
+
+#[test]
+fn listings_keep_their_entries_alive_past_round_ttl() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let pair = String::from_str(&env, "btc/usdc");
+    // open for joining for three months
+    let id = client.create_round(&creator, &pair, &(90 * 86_400), &60u64, &SettleMode::Spot);
+    for _ in 0..3 {
+        env.ledger().with_mut(|l| l.sequence_number += ROUND_TTL - DAY_IN_LEDGERS);
+        assert_eq!(client.list_rounds(&None, &Some(pair.clone()), &0, &10).rounds.len(), 1);
+        assert_eq!(client.rounds_by_creator(&creator, &0, &10).rounds.len(), 1);
+        assert_eq!(client.active_rounds(&pair, &0, &10).rounds.len(), 1);
+    }
+    // its active-set entries are still there to be removed when it ends
+    client.cancel(&id);
+    assert_eq!(client.active_rounds(&pair, &0, &10).rounds.len(), 0);
+}
+
+#[test]
+fn listing_pages_follow_cursor() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let btc = String::from_str(&env, "btc/usdc");
+    for _ in 0..5 {
+        client.create_round(&creator, &btc, &2u64, &3u64, &SettleMode::Spot);
+    }
+    let first = client.rounds_by_creator(&creator, &0, &2);
+    assert_eq!(first.rounds.len(), 2);
+    assert_eq!(first.next_cursor, Some(2));
+    let second = client.rounds_by_creator(&creator, &2, &2);
+    assert_eq!(second.rounds.get_unchecked(0).id, 2);
+    let last = client.rounds_by_creator(&creator, &4, &2);
+    assert_eq!(last.rounds.len(), 1);
+    assert_eq!(last.next_cursor, None);
+}
+
+fn range_round(env: &Env, client: &RoundEngineClient, creator: &Address, edges: &[i128]) -> u64 {
+    let pair = String::from_str(env, "btc/usdc");
+    client.create_range_round(creator, &pair, &2u64, &3u64, &SettleMode::Spot, &Vec::from_slice(env, edges))
+}
+
+#[test]
+fn range_round_pays_bucket_holding_settle_price() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    // settles 5s in at 105_000_000
+    let id = range_round(&env, &client, &creator, &[90_000_000, 100_000_000, 110_000_000, 120_000_000]);
+    let r = client.get_round(&id);
+    assert_eq!(r.pools.len(), 3);
+    assert_eq!(r.kind, RoundKind::Range(Vec::from_slice(&env, &[90_000_000, 100_000_000, 110_000_000, 120_000_000])));
+
+    let (a, b, c) = (player(&env, &client, id, 1_000), player(&env, &client, id, 1_000), player(&env, &client, id, 1_000));
+    client.join(&id, &a, &0u32, &100i128);
+    client.join(&id, &b, &1u32, &200i128);
+    client.join(&id, &c, &2u32, &300i128);
+    env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
+    client.lock(&id);
+    env.ledger().with_mut(|l| { l.timestamp += 3; l.sequence_number += 1; });
+    client.settle(&id);
+
+    assert_eq!(client.get_round(&id).winner, Some(1));
+    // the other buckets together form the losing pool: 200 + 200 * 400 / 200
+    assert_eq!(client.claim(&id, &b), 600);
+    assert_eq!(
+        client.try_claim(&id, &c).err(),
+        Some(Ok(soroban_sdk::Error::from_contract_error(Err::NotWinner as u32)))
+    );
+}
+
+#[test]
+fn range_round_outside_every_bucket_refunds() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let id = range_round(&env, &client, &creator, &[110_000_000, 120_000_000, 130_000_000]);
+    let (a, b) = (player(&env, &client, id, 1_000), player(&env, &client, id, 1_000));
+    client.join(&id, &a, &0u32, &100i128);
+    client.join(&id, &b, &1u32, &50i128);
+    env.ledger().with_mut(|l| { l.timestamp += 2; l.sequence_number += 1; });
+    client.lock(&id);
+    env.ledger().with_mut(|l| { l.timestamp += 3; l.sequence_number += 1; });
+    client.settle(&id);
+
+    assert_eq!(client.get_round(&id).winner, Some(2));
+    client.refund(&id, &a);
+    client.refund(&id, &b);
+    assert_eq!(balance(&env, &client, id, &a), 1_000);
+    assert_eq!(balance(&env, &client, id, &b), 1_000);
+}
+
+#[test]
+fn range_round_rejects_bad_side_and_edges() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let id = range_round(&env, &client, &creator, &[1, 2, 3]);
+    let who = player(&env, &client, id, 100);
+    assert_eq!(
+        client.try_join(&id, &who, &2u32, &10i128).err(),
+        Some(Ok(soroban_sdk::Error::from_contract_error(Err::BadSide as u32)))
+    );
+    // Up/Down rounds only have sides 0 and 1
+    let updown = client.create_round(&creator, &String::from_str(&env, "btc/usdc"), &2u64, &3u64, &SettleMode::Spot);
+    assert!(client.try_join(&updown, &who, &2u32, &10i128).is_err());
+
+    let pair = String::from_str(&env, "btc/usdc");
+    let bad = Some(Ok(soroban_sdk::Error::from_contract_error(Err::BadBuckets as u32)));
+    for edges in [&[1i128, 2][..], &[1, 3, 2], &[1, 1, 2], &[0; 18]] {
+        let edges = Vec::from_slice(&env, edges);
+        assert_eq!(client.try_create_range_round(&creator, &pair, &2u64, &3u64, &SettleMode::Spot, &edges).err(), bad);
+    }
+}
+
+#[test]
+fn create_round_requires_creator_auth() {
+    let (env, client, _admin, creator) = setup_plain();
+    let pair = String::from_str(&env, "btc/usdc");
+    // nobody can open rounds in someone else's name
+    assert!(client.try_create_round(&creator, &pair, &10u64, &10u64, &SettleMode::Spot).is_err());
+    let id = signed_round(&env, &client, &creator);
+    assert_eq!(client.rounds_by_creator(&creator, &0, &10).rounds.get_unchecked(0).id, id);
+}
+
+#[test]
+fn active_set_stays_consistent_as_rounds_leave() {
+    let (env, client, _admin, creator, _oracle) = setup_auth();
+    let btc = String::from_str(&env, "btc/usdc");
+    let ids: std::vec::Vec<u64> = (0..5).map(|_| client.create_round(&creator, &btc, &10u64, &10u64, &SettleMode::Spot)).collect();
+    client.cancel(&ids[1]);
+    client.cancel(&ids[4]);
+    client.cancel(&ids[0]);
+
+    let first = client.active_rounds(&btc, &0, &1);
+    assert_eq!(first.next_cursor, Some(1));
+    let rest = client.active_rounds(&btc, &1, &10);
+    assert_eq!(rest.next_cursor, None);
+    let mut active = std::vec![first.rounds.get_unchecked(0).id];
+    active.extend(rest.rounds.iter().map(|e| e.id));
+    active.sort();
+    assert_eq!(active, [ids[2], ids[3]]);
+
+    client.cancel(&ids[2]);
+    client.cancel(&ids[3]);
+    assert_eq!(client.active_rounds(&btc, &0, &10).rounds.len(), 0);
+    env.as_contract(&client.address, || {
+        assert!(!env.storage().persistent().has(&DataKey::ActiveLen(btc.clone())));
+        assert!(!env.storage().persistent().has(&DataKey::ActiveSlot(ids[3])));
+    });
+}